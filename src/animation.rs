@@ -0,0 +1,63 @@
+use crate::settings::UserSettings;
+use std::path::PathBuf;
+
+/// A zoom animation between two views, rendered offscreen to numbered PNG files
+pub(crate) struct Animation {
+    pub(crate) start: Option<UserSettings>,
+    pub(crate) end: Option<UserSettings>,
+    pub(crate) frames: u32,
+    /// Interpolate the iteration count between the start and end views instead of keeping the
+    /// start view's, so that deeper frames keep their detail
+    pub(crate) ramp_iterations: bool,
+    pub(crate) recording: Option<Recording>,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) struct Recording {
+    pub(crate) dir: PathBuf,
+    pub(crate) next_frame: u32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            frames: 300,
+            ramp_iterations: true,
+            recording: None,
+            error: None,
+        }
+    }
+}
+
+impl Animation {
+    /// The settings for the given frame, or `None` if the start or end view hasn't been set
+    pub(crate) fn frame_settings(&self, frame: u32) -> Option<UserSettings> {
+        let (start, end) = (self.start.as_ref()?, self.end.as_ref()?);
+        let t = frame as f64 / (self.frames.max(2) - 1) as f64;
+
+        let mut settings = start.clone();
+        // Zoom geometrically so the animation appears to move at a constant speed
+        settings.zoom = start.zoom * (end.zoom / start.zoom).powf(t);
+        settings.centre = [
+            start.centre[0] + (end.centre[0] - start.centre[0]) * t,
+            start.centre[1] + (end.centre[1] - start.centre[1]) * t,
+        ];
+        if self.ramp_iterations {
+            settings.iterations = (start.iterations as f64
+                + (end.iterations - start.iterations) as f64 * t)
+                .round() as i32;
+        }
+        Some(settings)
+    }
+
+    pub(crate) fn frame_path(&self, frame: u32) -> Option<PathBuf> {
+        Some(
+            self.recording
+                .as_ref()?
+                .dir
+                .join(format!("frame_{frame:05}.png")),
+        )
+    }
+}
@@ -0,0 +1,121 @@
+use crate::headless::{FractalRenderer, HeadlessRenderError};
+use crate::settings::{CustomShaderData, UserSettings};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// Size every benchmark view is rendered at, independent of any window
+const BENCHMARK_WIDTH: u32 = 1920;
+const BENCHMARK_HEIGHT: u32 = 1080;
+/// Frames timed for each view, after one untimed frame that builds the pipeline
+const BENCHMARK_FRAMES: u32 = 10;
+
+/// The views rendered by the benchmark. These shouldn't change, so that results from different
+/// versions can be compared.
+fn benchmark_views() -> Vec<(&'static str, UserSettings)> {
+    vec![
+        ("Mandelbrot set", UserSettings::default()),
+        (
+            "Deep zoom (high precision)",
+            UserSettings {
+                centre: [-0.743643887037151, 0.131825904205330],
+                zoom: 1e9,
+                iterations: 3000,
+                high_precision: true,
+                ..Default::default()
+            },
+        ),
+        (
+            "Julia set, 10000 iterations",
+            UserSettings {
+                julia_set: true,
+                julia_c: [-0.8, 0.156],
+                iterations: 10000,
+                ..Default::default()
+            },
+        ),
+        (
+            "Burning ship, 3x3 supersampling",
+            UserSettings {
+                centre: [-1.755, -0.03],
+                zoom: 20.0,
+                samples: 3,
+                shader_data: CustomShaderData {
+                    equation: "csquare(abs(z)) + c".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+#[derive(serde::Serialize)]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub frames: u32,
+    pub total_seconds: f64,
+    pub fps: f64,
+}
+
+/// Timings from `run_benchmark`. Each frame includes reading the image back from the GPU, so the
+/// numbers are lower than the live FPS counter would show for the same view.
+#[derive(serde::Serialize)]
+pub struct BenchmarkReport {
+    pub adapter: String,
+    pub width: u32,
+    pub height: u32,
+    pub results: Vec<BenchmarkResult>,
+    pub total_seconds: f64,
+}
+
+impl BenchmarkReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl Display for BenchmarkReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Adapter: {}", self.adapter)?;
+        writeln!(f, "Resolution: {}x{}", self.width, self.height)?;
+        for result in &self.results {
+            writeln!(
+                f,
+                "{}: {:.1} FPS ({} frames in {:.3}s)",
+                result.name, result.fps, result.frames, result.total_seconds
+            )?;
+        }
+        write!(f, "Total: {:.3}s", self.total_seconds)
+    }
+}
+
+/// Render a fixed set of views offscreen and time them, for comparing GPUs and versions
+pub fn run_benchmark() -> Result<BenchmarkReport, HeadlessRenderError> {
+    let mut renderer = FractalRenderer::new()?;
+    let mut results = Vec::new();
+    let mut total = Duration::ZERO;
+
+    for (name, settings) in benchmark_views() {
+        renderer.render(&settings, BENCHMARK_WIDTH, BENCHMARK_HEIGHT)?;
+        let start = Instant::now();
+        for _ in 0..BENCHMARK_FRAMES {
+            renderer.render(&settings, BENCHMARK_WIDTH, BENCHMARK_HEIGHT)?;
+        }
+        let elapsed = start.elapsed();
+        total += elapsed;
+        results.push(BenchmarkResult {
+            name,
+            frames: BENCHMARK_FRAMES,
+            total_seconds: elapsed.as_secs_f64(),
+            fps: BENCHMARK_FRAMES as f64 / elapsed.as_secs_f64(),
+        });
+    }
+
+    Ok(BenchmarkReport {
+        adapter: renderer.adapter_description(),
+        width: BENCHMARK_WIDTH,
+        height: BENCHMARK_HEIGHT,
+        results,
+        total_seconds: total.as_secs_f64(),
+    })
+}
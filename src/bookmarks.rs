@@ -0,0 +1,63 @@
+use crate::settings::UserSettings;
+
+/// Named views saved by the user. These are stored as JSON rather than using the export string
+/// format, so that bookmarks saved by older versions still load after `UserSettings` gains fields.
+pub(crate) type Bookmarks = Vec<(String, UserSettings)>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn bookmarks_path() -> Option<std::path::PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join(env!("CARGO_PKG_NAME"))
+            .join("bookmarks.json"),
+    )
+}
+
+#[cfg(target_arch = "wasm32")]
+const LOCAL_STORAGE_KEY: &str = "fractal_viewer_bookmarks";
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_bookmarks() -> Option<String> {
+    std::fs::read_to_string(bookmarks_path()?).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_bookmarks() -> Option<String> {
+    local_storage()?.get_item(LOCAL_STORAGE_KEY).ok()?
+}
+
+pub(crate) fn load() -> Bookmarks {
+    read_bookmarks()
+        .and_then(|json| match serde_json::from_str(&json) {
+            Ok(bookmarks) => Some(bookmarks),
+            Err(e) => {
+                log::warn!("Failed to load bookmarks: {e}");
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save(bookmarks: &Bookmarks) -> Result<(), String> {
+    let path = bookmarks_path().ok_or("Could not find config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(bookmarks).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save(bookmarks: &Bookmarks) -> Result<(), String> {
+    let json = serde_json::to_string(bookmarks).map_err(|e| e.to_string())?;
+    local_storage()
+        .ok_or("Local storage is not available")?
+        .set_item(LOCAL_STORAGE_KEY, &json)
+        .map_err(|_| "Failed to write to local storage".to_string())
+}
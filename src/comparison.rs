@@ -0,0 +1,105 @@
+use crate::palette::PaletteTexture;
+use crate::settings::{CustomShaderData, UserSettings};
+use crate::uniforms::Uniforms;
+use crate::{CompiledShader, RendererState, ShaderError};
+use bytemuck::Zeroable;
+use egui_wgpu::wgpu;
+use std::sync::Arc;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferUsages, Queue, RenderPass,
+};
+
+/// A second set of settings, drawn to the right of a divider over the main view so that the two
+/// can be compared. The view itself is always taken from the main settings.
+pub(crate) struct Comparison {
+    pub(crate) settings: UserSettings,
+    /// Position of the divider as a fraction of the view's width
+    pub(crate) divider: f32,
+    /// Set if the compared settings' shader is invalid, in which case that side isn't drawn
+    pub(crate) error: Option<ShaderError>,
+}
+
+impl Comparison {
+    /// The compared settings, showing the same region as `view`
+    pub(crate) fn settings_for_view(&self, view: &UserSettings) -> UserSettings {
+        UserSettings {
+            centre: view.centre,
+            zoom: view.zoom,
+            framing: view.framing,
+            ..self.settings.clone()
+        }
+    }
+}
+
+/// Draws the compared settings with their own uniforms, pipeline and palette, so that any of
+/// them can differ from the main view's
+pub(crate) struct ComparisonRenderer {
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    palette: PaletteTexture,
+    shader_data: CustomShaderData,
+    template: Arc<str>,
+    compiled: CompiledShader,
+}
+
+impl ComparisonRenderer {
+    pub(crate) fn new(
+        state: &RendererState,
+        queue: &Queue,
+        shader_data: &CustomShaderData,
+        template: &Arc<str>,
+    ) -> Self {
+        let uniform_buffer = state.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fv_comparison_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[Uniforms::zeroed()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = state.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_comparison_bind_group"),
+            layout: &state.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            uniform_buffer,
+            bind_group,
+            palette: state.palette.new_compatible(&state.device, queue),
+            shader_data: shader_data.clone(),
+            template: Arc::clone(template),
+            compiled: state.compile(shader_data.shader(template), 1),
+        }
+    }
+
+    /// Write the uniforms and palette, recompiling first if the shader has changed. Compiling
+    /// blocks, but only happens when the compared settings are swapped with the main ones or the
+    /// shader is reloaded.
+    pub(crate) fn prepare(
+        &mut self,
+        state: &RendererState,
+        queue: &Queue,
+        uniforms: Uniforms,
+        shader_data: &CustomShaderData,
+        template: &Arc<str>,
+        palette: &[u8],
+    ) {
+        if *shader_data != self.shader_data || !Arc::ptr_eq(template, &self.template) {
+            self.compiled = state.compile(shader_data.shader(template), 1);
+            self.shader_data = shader_data.clone();
+            self.template = Arc::clone(template);
+        }
+        self.palette.set(&state.device, queue, palette);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    pub(crate) fn paint(&self, render_pass: &mut RenderPass<'_>) {
+        render_pass.set_pipeline(&self.compiled.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, self.palette.bind_group(), &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
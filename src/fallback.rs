@@ -0,0 +1,55 @@
+use eframe::egui::{self, Context};
+use eframe::Frame;
+
+const HEADING: &str = "No compatible graphics device found";
+
+/// Suggestions for getting the renderer working, shown when it can't be created
+#[cfg(not(target_arch = "wasm32"))]
+pub const TROUBLESHOOTING: &str = "fractal_viewer needs a GPU that supports Vulkan, Metal, DirectX 12 or OpenGL ES 3.
+* Make sure your graphics drivers are up to date.
+* Try another graphics backend with --backend <vulkan|metal|dx12|gl>, or another GPU with --power-preference <high|low>.
+* Run with RUST_LOG=wgpu=warn for details of what went wrong.";
+#[cfg(target_arch = "wasm32")]
+pub const TROUBLESHOOTING: &str = "fractal_viewer needs WebGPU or WebGL 2.
+* Make sure hardware acceleration is enabled in your browser's settings.
+* WebGPU may need enabling in your browser; see https://github.com/gpuweb/gpuweb/wiki/Implementation-Status.
+* Otherwise, try the desktop version.";
+
+/// Shown instead of [`FractalViewerApp`](crate::FractalViewerApp) when eframe starts without a
+/// wgpu renderer, to explain why nothing can be drawn rather than panicking
+#[derive(Default)]
+pub struct RendererUnavailableApp;
+
+impl eframe::App for RendererUnavailableApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(HEADING);
+            ui.label(TROUBLESHOOTING);
+        });
+    }
+}
+
+/// Explain in a message box that the renderer couldn't be started. eframe gives up before opening
+/// a window when it can't create one, so `RendererUnavailableApp` never gets the chance.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_native_error(error: &str) {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title(HEADING)
+        .set_description(format!("{TROUBLESHOOTING}\n\n{error}"))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// Replace the canvas with an explanation when the web renderer couldn't be started, which would
+/// otherwise leave the page blank
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn show_web_error(canvas: &web_sys::Element, error: &str) {
+    let message = format!("{HEADING}\n{TROUBLESHOOTING}\n\n{error}");
+    if let Some(document) = canvas.owner_document() {
+        if let Ok(element) = document.create_element("pre") {
+            element.set_text_content(Some(&message));
+            let _ = canvas.replace_with_with_node_1(&element);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::headless::FractalRenderer;
+use crate::settings::{CustomShaderData, EquationPreset, UserSettings, EQUATION_PRESETS};
+use eframe::egui::{self, ColorImage, Context, TextureHandle, TextureOptions, Ui, Vec2};
+
+/// Width and height of each thumbnail in pixels
+const THUMBNAIL_SIZE: u32 = 96;
+
+/// Thumbnails of each built in equation, rendered once with the headless renderer and kept as
+/// textures so they don't have to be rendered again every frame
+pub(crate) struct PresetGallery {
+    thumbnails: Result<Vec<TextureHandle>, String>,
+}
+
+impl PresetGallery {
+    pub(crate) fn new(ctx: &Context) -> Self {
+        Self {
+            thumbnails: Self::render_thumbnails(ctx),
+        }
+    }
+
+    fn render_thumbnails(ctx: &Context) -> Result<Vec<TextureHandle>, String> {
+        let mut renderer = FractalRenderer::new().map_err(|e| e.to_string())?;
+        EQUATION_PRESETS
+            .iter()
+            .map(|preset| {
+                let settings = UserSettings {
+                    shader_data: CustomShaderData {
+                        equation: preset.equation.to_string(),
+                        colour: preset.colour.to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let pixels = renderer
+                    .render(&settings, THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+                    .map_err(|e| e.to_string())?;
+                let image = ColorImage::from_rgba_unmultiplied(
+                    [THUMBNAIL_SIZE as usize; 2],
+                    pixels.as_slice(),
+                );
+                Ok(ctx.load_texture(
+                    format!("preset_{}", preset.name),
+                    image,
+                    TextureOptions::LINEAR,
+                ))
+            })
+            .collect()
+    }
+
+    /// Show the thumbnails as buttons, returning the preset that was clicked, if any
+    pub(crate) fn show(&self, ui: &mut Ui) -> Option<&'static EquationPreset> {
+        let thumbnails = match &self.thumbnails {
+            Ok(thumbnails) => thumbnails,
+            Err(e) => {
+                ui.label(format!("Couldn't render thumbnails: {e}"));
+                return None;
+            }
+        };
+
+        let mut clicked = None;
+        ui.horizontal_wrapped(|ui| {
+            for (preset, texture) in EQUATION_PRESETS.iter().zip(thumbnails) {
+                let size = Vec2::splat(THUMBNAIL_SIZE as f32);
+                if ui
+                    .add(egui::ImageButton::new((texture.id(), size)))
+                    .on_hover_text(preset.name)
+                    .clicked()
+                {
+                    clicked = Some(preset);
+                }
+            }
+        });
+        clicked
+    }
+}
@@ -0,0 +1,123 @@
+use crate::settings::{CustomShaderData, UserSettings};
+use crate::uniforms::Uniforms;
+use crate::{backend_name, FvRenderer, RendererState, ShaderValidator};
+use eframe::egui::Vec2;
+use egui_wgpu::wgpu;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use wgpu::{Queue, TextureFormat};
+
+#[derive(Debug)]
+pub enum HeadlessRenderError {
+    NoAdapter,
+    RequestDeviceFailed(String),
+    InvalidShader(String),
+    /// The GPU ran out of memory or the device was lost while rendering
+    RenderFailed(String),
+}
+
+impl Display for HeadlessRenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadlessRenderError::NoAdapter => write!(f, "No suitable graphics adapter found"),
+            HeadlessRenderError::RequestDeviceFailed(e) => {
+                write!(f, "Failed to create graphics device: {e}")
+            }
+            HeadlessRenderError::InvalidShader(e) => {
+                write!(f, "Invalid equation or colour expression: {e}")
+            }
+            HeadlessRenderError::RenderFailed(e) => write!(f, "Rendering failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessRenderError {}
+
+/// Renders fractals to RGBA images without a window, using its own wgpu device.
+///
+/// The pipeline is only rebuilt when the equation or colour changes, so rendering many images
+/// with the same renderer is much faster than calling [`render_to_rgba`] for each.
+pub struct FractalRenderer {
+    queue: Queue,
+    renderer: FvRenderer,
+    shader_data: CustomShaderData,
+    shader_validator: ShaderValidator,
+    adapter_info: wgpu::AdapterInfo,
+}
+
+impl FractalRenderer {
+    pub fn new() -> Result<Self, HeadlessRenderError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or(HeadlessRenderError::NoAdapter)?;
+        let adapter_info = adapter.get_info();
+        let (device, queue) = pollster::block_on(adapter.request_device(&Default::default(), None))
+            .map_err(|e| HeadlessRenderError::RequestDeviceFailed(e.to_string()))?;
+
+        let shader_data = CustomShaderData::default();
+        let shader_validator = ShaderValidator::new();
+        let state = RendererState::new(
+            Arc::new(device),
+            &queue,
+            TextureFormat::Rgba8Unorm.into(),
+            Uniforms::new(Vec2::splat(1.0), &UserSettings::default()),
+        );
+
+        Ok(Self {
+            queue,
+            renderer: FvRenderer::new(state, shader_validator.shader(&shader_data), None),
+            shader_data,
+            shader_validator,
+            adapter_info,
+        })
+    }
+
+    /// The name of the GPU and the backend used to render with it
+    pub fn adapter_description(&self) -> String {
+        format!(
+            "{} ({})",
+            self.adapter_info.name,
+            backend_name(self.adapter_info.backend)
+        )
+    }
+
+    /// Render the given settings, returning tightly packed RGBA rows. The image shows the same
+    /// region as a window of the same size would.
+    pub fn render(
+        &mut self,
+        settings: &UserSettings,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, HeadlessRenderError> {
+        if settings.shader_data != self.shader_data {
+            // An invalid shader would otherwise only be reported by wgpu's error handler
+            self.shader_validator
+                .validate_shader(&settings.shader_data)
+                .map_err(|e| HeadlessRenderError::InvalidShader(e.to_string()))?;
+            self.renderer
+                .set_shader(self.shader_validator.shader(&settings.shader_data));
+            self.shader_data = settings.shader_data.clone();
+        }
+
+        self.renderer
+            .state
+            .set_palette(&self.queue, &settings.palette);
+        let uniforms = Uniforms::new(Vec2::new(width as f32, height as f32), settings);
+        self.renderer
+            .render_to_rgba(&self.queue, uniforms, width, height)
+            .map_err(HeadlessRenderError::RenderFailed)
+    }
+}
+
+/// Render a single image without a window. See [`FractalRenderer`] for rendering several.
+pub fn render_to_rgba(
+    settings: &UserSettings,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, HeadlessRenderError> {
+    FractalRenderer::new()?.render(settings, width, height)
+}
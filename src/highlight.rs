@@ -0,0 +1,98 @@
+use eframe::egui::text::{LayoutJob, TextFormat};
+use eframe::egui::{Color32, Galley, TextStyle, Ui};
+use std::sync::Arc;
+
+const KEYWORDS: &[&str] = &[
+    "let", "var", "const", "fn", "return", "if", "else", "for", "while", "loop", "break",
+    "continue", "switch", "case", "default", "struct", "true", "false",
+];
+
+const TYPES: &[&str] = &[
+    "f32", "i32", "u32", "bool", "vec2", "vec3", "vec4", "mat2x2", "mat3x3", "mat4x4", "array",
+];
+
+#[derive(Copy, Clone, PartialEq)]
+enum Token {
+    Comment,
+    Keyword,
+    Type,
+    Function,
+    Number,
+    Other,
+}
+
+/// Split WGSL source into byte ranges tagged with what kind of token they are. Anything not
+/// recognised is left as `Other`, so the ranges always cover the whole input.
+fn tokenise(code: &str) -> Vec<(usize, usize, Token)> {
+    let bytes = code.as_bytes();
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let rest = &code[start..];
+        let first = bytes[start];
+        let (len, token) = if rest.starts_with("//") {
+            (rest.find('\n').unwrap_or(rest.len()), Token::Comment)
+        } else if first.is_ascii_alphabetic() || first == b'_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            let token = if KEYWORDS.contains(&word) {
+                Token::Keyword
+            } else if TYPES.contains(&word) {
+                Token::Type
+            } else if rest[len..].trim_start().starts_with('(') {
+                Token::Function
+            } else {
+                Token::Other
+            };
+            (len, token)
+        } else if first.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+                .unwrap_or(rest.len());
+            (len, Token::Number)
+        } else {
+            (rest.chars().next().map_or(1, char::len_utf8), Token::Other)
+        };
+
+        match tokens.last_mut() {
+            Some((_, end, last)) if *last == token => *end += len,
+            _ => tokens.push((start, start + len, token)),
+        }
+        start += len;
+    }
+
+    tokens
+}
+
+/// Lay out WGSL code in a monospace font with keywords, types, function calls, numbers and
+/// comments coloured, for use as a `TextEdit` layouter.
+pub(crate) fn layout_wgsl(ui: &Ui, code: &str, wrap_width: f32) -> Arc<Galley> {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let dark = ui.visuals().dark_mode;
+    let colour = |token| match (token, dark) {
+        (Token::Comment, _) => Color32::GRAY,
+        (Token::Keyword, true) => Color32::from_rgb(255, 100, 100),
+        (Token::Keyword, false) => Color32::from_rgb(200, 30, 30),
+        (Token::Type, true) => Color32::from_rgb(110, 200, 255),
+        (Token::Type, false) => Color32::from_rgb(0, 100, 180),
+        (Token::Function, true) => Color32::from_rgb(220, 200, 110),
+        (Token::Function, false) => Color32::from_rgb(140, 100, 0),
+        (Token::Number, true) => Color32::from_rgb(140, 220, 140),
+        (Token::Number, false) => Color32::from_rgb(20, 130, 20),
+        (Token::Other, _) => ui.visuals().text_color(),
+    };
+
+    let mut job = LayoutJob::default();
+    for (start, end, token) in tokenise(code) {
+        job.append(
+            &code[start..end],
+            0.0,
+            TextFormat::simple(font_id.clone(), colour(token)),
+        );
+    }
+    job.wrap.max_width = wrap_width;
+    ui.fonts(|fonts| fonts.layout_job(job))
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the shader file's modification time is checked
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches an external copy of `shader.wgsl` and loads it whenever it changes, so the shader can
+/// be worked on without rebuilding
+pub(crate) struct ShaderWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Option<Instant>,
+    /// The last error, kept so that it is only reported once
+    error: Option<String>,
+}
+
+impl ShaderWatcher {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            last_poll: None,
+            error: None,
+        }
+    }
+
+    /// Load the shader if it has changed since it was last loaded, returning it if it was.
+    /// Errors reading the file are only returned the first time they happen.
+    pub(crate) fn poll(&mut self) -> Result<Option<String>, String> {
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL)
+        {
+            return Ok(None);
+        }
+        self.last_poll = Some(Instant::now());
+
+        match self.load() {
+            Ok(loaded) => {
+                self.error = None;
+                Ok(loaded)
+            }
+            Err(e) if self.error.as_ref() == Some(&e) => Ok(None),
+            Err(e) => {
+                self.error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    fn load(&mut self) -> Result<Option<String>, String> {
+        let error = |e: std::io::Error| format!("Couldn't read {}: {e}", self.path.display());
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(error)?;
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+        let source = std::fs::read_to_string(&self.path).map_err(error)?;
+        self.modified = Some(modified);
+        Ok(Some(source))
+    }
+}
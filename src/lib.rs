@@ -1,255 +1,1895 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod animation;
+#[cfg(not(target_arch = "wasm32"))]
+mod benchmark;
+mod bookmarks;
+mod comparison;
+mod fallback;
+#[cfg(not(target_arch = "wasm32"))]
+mod gallery;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod highlight;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod orbit;
+mod palette;
+mod preview;
+mod probe;
+mod scaled;
+mod sections;
 mod settings;
 mod uniforms;
 #[cfg(target_arch = "wasm32")]
 mod web;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::benchmark::{run_benchmark, BenchmarkReport, BenchmarkResult};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::fallback::show_native_error;
+pub use crate::fallback::{RendererUnavailableApp, TROUBLESHOOTING};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::headless::{render_to_rgba, FractalRenderer, HeadlessRenderError};
+pub use crate::settings::{InvalidSettingsImportError, UserSettings};
+
 use egui_wgpu::wgpu;
 #[cfg(not(target_arch = "wasm32"))]
 use egui_wgpu::wgpu::naga;
 
-use crate::settings::{CustomShaderData, UserSettings};
-use crate::uniforms::{calculate_scale, Uniforms};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::animation::{Animation, Recording};
+use crate::bookmarks::Bookmarks;
+use crate::comparison::{Comparison, ComparisonRenderer};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gallery::PresetGallery;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hot_reload::ShaderWatcher;
+use crate::orbit::supports_orbit;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::palette::decode_palette_png;
+use crate::palette::PaletteTexture;
+use crate::preview::{JuliaPreview, PREVIEW_MAX_ITERATIONS, PREVIEW_SIZE};
+use crate::probe::{IterationProbe, ProbeResult};
+use crate::scaled::ScaledRenderer;
+use crate::sections::OpenSections;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::settings::PNG_SETTINGS_KEYWORD;
+use crate::settings::{
+    multibrot_equation, ColourPreset, CustomShaderData, FractalMode, FrameRateLimit, Framing,
+    InvertColours, OrbitTrapShape, ShaderInput, COLOUR_EXPRESSION_PRESETS, DEFAULT_COLOUR,
+    DEFAULT_ESCAPE_CONDITION, DEFAULT_INTERIOR_COLOUR, DISTANCE_ESTIMATION_COLOUR,
+    EQUATION_PRESETS, MAX_ROOTS, MULTIBROT_POWERS, NEWTON_DERIVATIVE, NEWTON_EQUATION,
+    ORBIT_TRAP_COLOUR,
+};
+use crate::uniforms::{
+    calculate_scale, complex_to_pointer, effective_iterations, julia_morph, pointer_to_complex,
+    precision_exhausted, Uniforms, GREYSCALE_ITERATIONS, ITERATION_PROBE, ITERATION_STATS,
+    SRGB_TARGET,
+};
 #[allow(unused_imports)] // eframe::egui::ViewportCommand used on native but not web
 use eframe::egui::{
-    Color32, Context, Key, PaintCallbackInfo, PointerButton, TextEdit, ViewportCommand,
+    Color32, Context, Key, Modifiers, PaintCallbackInfo, PointerButton, Pos2, Rect, TextEdit, Vec2,
+    ViewportCommand,
 };
 use eframe::{egui, Frame};
 use egui_wgpu::{CallbackResources, ScreenDescriptor};
 use instant::Instant;
 use naga::valid::{Capabilities, ValidationFlags};
 use std::collections::VecDeque;
+use std::fmt::Display;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
     Backend, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
     BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType,
-    BufferUsages, ColorTargetState, CommandBuffer, CommandEncoder, Device, FragmentState,
-    MultisampleState, PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipeline,
-    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, VertexState,
+    BufferUsages, ColorTargetState, CommandBuffer, CommandEncoder, Device, DeviceLostReason,
+    Features, FragmentState, MultisampleState, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, TextureFormat, TextureFormatFeatureFlags, VertexState,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use wgpu::{
+    BufferDescriptor, Color, CommandEncoderDescriptor, ErrorFilter, Extent3d, ImageCopyBuffer,
+    ImageCopyTexture, ImageDataLayout, LoadOp, Maintain, MapMode, Operations, Origin3d,
+    RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
 static SHADER: &str = include_str!("shader.wgsl");
 
-fn validate_shader(options: &CustomShaderData) -> Result<(), String> {
-    let shader_src = options.shader();
+const COLOUR_PRESETS_KEY: &str = "colour_presets";
+const OPEN_SECTIONS_KEY: &str = "open_sections";
+const MSAA_SAMPLES_KEY: &str = "msaa_samples";
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_KEY: &str = "settings";
+
+/// Keyboard panning speed in pixels per second
+const KEYBOARD_PAN_SPEED: f64 = 500.0;
+/// Factor the zoom changes by per second when zooming with the keyboard
+const KEYBOARD_ZOOM_RATE: f64 = 2.0;
+/// Default factor the zoom changes by per second when auto zoom is enabled
+const AUTO_ZOOM_RATE: f64 = 2.0;
+
+/// Time in seconds for the speed of the view after a drag is released to fall by a factor of e
+const PAN_INERTIA_DECAY_TIME: f32 = 0.15;
+/// Speed in points per second below which the view stops moving after a drag
+const PAN_INERTIA_MIN_SPEED: f32 = 5.0;
+
+/// Maximum number of settings snapshots kept for undo
+const UNDO_HISTORY_LENGTH: usize = 128;
+/// How long the view has to stay still before a change is added to the undo history
+const UNDO_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// How much the iteration count is multiplied by when refining the current view
+const REFINE_ITERATION_FACTOR: i32 = 4;
+/// Supersampling factor used when refining the current view, the most the shader supports
+const REFINE_SAMPLES: u32 = 4;
+
+/// Resolution of the longest side of the view when rendering it to count iterations
+const STATS_RESOLUTION: f32 = 128.0;
+
+/// Roughly how far apart grid lines are drawn in points. The spacing in the complex plane is
+/// rounded to a power of ten, so the lines are between about a third and three times this apart.
+const GRID_TARGET_SPACING: f64 = 100.0;
+const MAX_GRID_LINES: usize = 1000;
+
+/// Smallest selection to zoom into, in points along each axis, so that a tiny drag doesn't zoom
+/// in by a huge amount
+const MIN_SELECTION_SIZE: f32 = 16.0;
+
+/// Scroll distance in points that zooms in by a factor of 2 at the default sensitivity
+const SCROLL_ZOOM_DIVISOR: f64 = 300.0;
+
+/// Upper end of the zoom slider, which widens if a larger zoom is typed in
+const ZOOM_SLIDER_MAX: f64 = 100000.0;
+const MAX_ZOOM: f64 = 1e300;
+/// Zooming out further than this only shows the exterior of the set, and the scale becomes
+/// meaningless as the zoom approaches zero
+const MIN_ZOOM: f64 = 1e-6;
+/// Upper end of the iterations slider, which widens if a larger count is typed in
+const ITERATIONS_SLIDER_MAX: i32 = 10000;
+/// Leaves room for refinement to multiply the iteration count without overflowing
+const MAX_ITERATIONS: i32 = i32::MAX / REFINE_ITERATION_FACTOR;
+
+/// Modifiers for the reset all shortcut. Ctrl+R reloads the page in browsers, so use Alt+R on web.
+#[cfg(not(target_arch = "wasm32"))]
+const RESET_MODIFIERS: Modifiers = Modifiers::COMMAND;
+#[cfg(target_arch = "wasm32")]
+const RESET_MODIFIERS: Modifiers = Modifiers::ALT;
+#[cfg(not(target_arch = "wasm32"))]
+const RESET_SHORTCUT: &str = "[Ctrl+R]";
+#[cfg(target_arch = "wasm32")]
+const RESET_SHORTCUT: &str = "[Alt+R]";
+
+/// Iteration count above which the view may take long enough to render that the browser stops
+/// the shader on WebGL, which makes it go black
+#[cfg(target_arch = "wasm32")]
+const WEBGL_SAFE_ITERATIONS: i32 = 5000;
+
+/// Width of the area the split view's divider can be dragged by, in points
+const COMPARISON_HANDLE_WIDTH: f32 = 8.0;
+
+/// MSAA sample counts offered, if the GPU supports them
+const MSAA_SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+/// Largest tile rendered at once when rendering an image, which keeps the readback buffer within
+/// wgpu's default buffer size limit
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_TILE_SIZE: u32 = 4096;
+/// Largest screenshot size allowed along each side. Larger images are rendered in tiles.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_SCREENSHOT_SIZE: u32 = 16384;
+
+/// Seconds the animated morph between the Mandelbrot and Julia sets takes
+const JULIA_MORPH_DURATION: f32 = 2.0;
+
+/// How often continuous effects like auto zoom repaint while the window is unfocused or hidden
+const BACKGROUND_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lowest render scale allowed, as a fraction of the view's resolution
+const MIN_RENDER_SCALE: f32 = 0.25;
+
+/// How much the iteration count is divided by while the user is panning or zooming
+const INTERACTION_ITERATION_DIVISOR: i32 = 4;
+/// How long after the last interaction the view is rendered at full quality again
+const INTERACTION_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Default number of frames the FPS counter is averaged over
+const DEFAULT_FPS_WINDOW: usize = 200;
+/// Most frames the FPS counter can be set to average over
+const MAX_FPS_WINDOW: usize = 1000;
+
+#[derive(Debug, Clone)]
+enum ShaderErrorLocation {
+    /// The error is in something the user typed
+    Input {
+        input: ShaderInput,
+        line: usize,
+        column: usize,
+        /// The offending line of the input, followed by a line with a caret under the error
+        snippet: String,
+    },
+    /// The error is somewhere else in the generated shader
+    Generated { line: u32, column: u32 },
+}
+
+/// Why the generated shader was rejected, with where in it the problem is if naga reports it
+#[derive(Debug, Clone)]
+pub enum ShaderError {
+    /// The shader isn't valid WGSL, e.g. because of a typo or unbalanced brackets
+    Parse(ShaderErrorDetails),
+    /// The shader parsed but doesn't make sense, e.g. because an expression has the wrong type
+    Validation(ShaderErrorDetails),
+    /// The shader being reloaded from disk couldn't be read
+    Reload(ShaderErrorDetails),
+}
+
+impl ShaderError {
+    fn details(&self) -> &ShaderErrorDetails {
+        match self {
+            ShaderError::Parse(details)
+            | ShaderError::Validation(details)
+            | ShaderError::Reload(details) => details,
+        }
+    }
+
+    /// What kind of error this is, to show before the message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ShaderError::Parse(_) => "Syntax error",
+            ShaderError::Validation(_) => "Invalid shader",
+            ShaderError::Reload(_) => "Shader reload failed",
+        }
+    }
+}
+
+impl Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.details())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// The message naga gave for a [`ShaderError`], and where the problem is. Its `Display` output
+/// points at the offending line of the user's input when the error is in one.
+#[derive(Debug, Clone)]
+pub struct ShaderErrorDetails {
+    message: String,
+    location: Option<ShaderErrorLocation>,
+}
+
+impl ShaderErrorDetails {
+    /// naga's message, without the location
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn new(
+        message: String,
+        location: Option<naga::SourceLocation>,
+        options: &CustomShaderData,
+        inputs: &[(Range<usize>, ShaderInput)],
+    ) -> Self {
+        let location = location.map(|location| {
+            let offset = location.offset as usize;
+            let Some((range, input)) = inputs
+                .iter()
+                .find(|(range, _)| range.contains(&offset) || range.end == offset)
+            else {
+                return ShaderErrorLocation::Generated {
+                    line: location.line_number,
+                    column: location.line_position,
+                };
+            };
+
+            let text = options.input(*input);
+            let offset = offset - range.start;
+            let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+            let column = text[line_start..offset].chars().count();
+            let length = text[offset..line_end]
+                .chars()
+                .count()
+                .min(location.length as usize)
+                .max(1);
+
+            ShaderErrorLocation::Input {
+                input: *input,
+                line: text[..line_start].matches('\n').count() + 1,
+                column: column + 1,
+                snippet: format!(
+                    "{}\n{}{}",
+                    &text[line_start..line_end],
+                    " ".repeat(column),
+                    "^".repeat(length)
+                ),
+            }
+        });
+
+        Self { message, location }
+    }
+}
+
+impl Display for ShaderErrorDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        match &self.location {
+            Some(ShaderErrorLocation::Input {
+                input,
+                line,
+                column,
+                snippet,
+            }) => write!(
+                f,
+                " (in {input} at line {line}, column {column})\n{snippet}"
+            ),
+            Some(ShaderErrorLocation::Generated { line, column }) => write!(
+                f,
+                " (at line {line}, column {column} of the generated shader)"
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+struct ShaderValidator {
+    frontend: naga::front::wgsl::Frontend,
+    validator: naga::valid::Validator,
+    /// The shader the user inputs are inserted into, which is the embedded one unless one was
+    /// loaded from disk
+    template: Arc<str>,
+    cache: Option<(CustomShaderData, Result<(), ShaderError>)>,
+}
+
+impl ShaderValidator {
+    fn new() -> Self {
+        Self::with_template(SHADER.into())
+    }
+
+    fn with_template(template: Arc<str>) -> Self {
+        Self {
+            frontend: naga::front::wgsl::Frontend::new(),
+            validator: naga::valid::Validator::new(ValidationFlags::all(), Capabilities::empty()),
+            template,
+            cache: None,
+        }
+    }
+
+    /// The shader generated from `options`, which is what gets validated and so what the renderer
+    /// should compile
+    fn shader(&self, options: &CustomShaderData) -> String {
+        options.shader(&self.template)
+    }
+
+    /// Validate the shader generated from `options`, reusing the result of the previous call if
+    /// the custom shader data hasn't changed since then. This uses the same source the renderer
+    /// compiles (`ShaderValidator::shader`, including the additional code), so the result always
+    /// matches pipeline creation.
+    fn validate_shader(&mut self, options: &CustomShaderData) -> Result<(), ShaderError> {
+        if let Some((cached_options, result)) = &self.cache {
+            if cached_options == options {
+                return result.clone();
+            }
+        }
+
+        let result = self.validate_uncached(options);
+        self.cache = Some((options.clone(), result.clone()));
+        result
+    }
+
+    fn validate_uncached(&mut self, options: &CustomShaderData) -> Result<(), ShaderError> {
+        let (shader_src, inputs) = options.shader_with_inputs(&self.template);
+
+        let module = self.frontend.parse(&shader_src).map_err(|e| {
+            ShaderError::Parse(ShaderErrorDetails::new(
+                e.message().to_string(),
+                e.location(&shader_src),
+                options,
+                &inputs,
+            ))
+        })?;
+        self.validator.validate(&module).map_err(|e| {
+            ShaderError::Validation(ShaderErrorDetails::new(
+                e.as_inner().to_string(),
+                e.location(&shader_src),
+                options,
+                &inputs,
+            ))
+        })?;
+        Ok(())
+    }
+}
 
-    let module = naga::front::wgsl::Frontend::new()
-        .parse(&shader_src)
-        .map_err(|e| e.to_string())?;
-    naga::valid::Validator::new(ValidationFlags::all(), Capabilities::empty())
-        .validate(&module)
-        .map_err(|e| e.to_string())?;
-    Ok(())
+/// Check that an equation and colour expression, along with any additional code they use, make a
+/// valid shader, without needing a GPU. This is the same check the app does before compiling.
+pub fn validate_expression(
+    equation: &str,
+    colour: &str,
+    additional: &str,
+) -> Result<(), ShaderError> {
+    ShaderValidator::new().validate_uncached(&CustomShaderData {
+        equation: equation.to_string(),
+        colour: colour.to_string(),
+        additional: additional.to_string(),
+        ..Default::default()
+    })
 }
 
 pub struct FractalViewerApp {
     settings: UserSettings,
+    /// The settings as of the last change added to the undo history
+    committed_settings: UserSettings,
+    undo_stack: VecDeque<UserSettings>,
+    redo_stack: Vec<UserSettings>,
+    last_scroll: Option<Instant>,
+    /// Last time the view was dragged or scrolled, used to render at reduced quality while moving
+    last_interaction: Option<Instant>,
     last_frame: Instant,
     prev_frame_time: Duration,
     backend: &'static str,
     driver_info: String,
+    /// Name of the GPU, which browsers may leave empty
+    adapter_name: String,
+    /// The device's limits, shown with the backend
+    device_limits: wgpu::Limits,
+    /// Why the adapter in use isn't the one that was asked for, if it isn't
+    adapter_note: Option<String>,
+    /// MSAA sample counts the GPU supports for the render target
+    supported_sample_counts: Vec<u32>,
+    /// Hardware MSAA sample count chosen for the on-screen view, or 1 for none. This depends on
+    /// the GPU, so it is saved separately from the settings rather than being exported with them.
+    msaa_samples: u32,
+    /// Set from wgpu's callbacks if the device is lost or reports an error, instead of panicking
+    gpu_error: Arc<Mutex<Option<String>>>,
+    /// Set if the device is lost, after which nothing is drawn with it. A lost device can't be
+    /// recovered, so the app has to be restarted.
+    device_lost: Arc<AtomicBool>,
     show_ui: bool,
+    auto_zoom: bool,
+    auto_zoom_rate: f64,
+    /// Why auto zoom last stopped by itself
+    auto_zoom_note: Option<String>,
+    /// Contents of the exact zoom field, which shows the current zoom while it isn't being edited
+    zoom_text: String,
+    zoom_text_error: Option<&'static str>,
+    julia_follows_centre: bool,
     recompile_shader: bool,
-    shader_error: Option<String>,
+    shader_error: Option<ShaderError>,
+    shader_validator: ShaderValidator,
     import_error: Option<String>,
+    /// Whether the "Reset all" confirmation is being shown
+    confirm_reset: bool,
+    bookmarks: Bookmarks,
+    bookmark_name: String,
+    bookmark_error: Option<String>,
+    colour_presets: Vec<ColourPreset>,
+    colour_preset_name: String,
+    /// Which sections of the settings window are expanded, kept across restarts
+    open_sections: OpenSections,
+    colour_preset_import: String,
+    colour_preset_error: Option<String>,
     fps_samples: VecDeque<f32>,
+    /// Number of frames the FPS counter is averaged over. Fewer follow changes more quickly.
+    fps_window: usize,
+    /// Show the FPS of the last frame instead of the average
+    show_instant_fps: bool,
+    /// Whether the window is unfocused or hidden, so continuous effects should slow down
+    in_background: bool,
+    view_size: Vec2,
+    /// Size of the view the last time it was painted with a non-zero size
+    painted_size: Option<Vec2>,
+    hover_pos: Option<Pos2>,
+    /// Where a Shift+drag to select an area to zoom into started
+    selection_start: Option<Pos2>,
+    /// Speed the view keeps moving at after a drag is released, in points per second
+    pan_velocity: Vec2,
+    show_iteration_probe: bool,
+    show_render_stats: bool,
+    average_iterations: Option<f32>,
+    show_julia_preview: bool,
+    /// Settings shown to the right of a divider for comparison, if comparing
+    comparison: Option<Comparison>,
+    show_orbit: bool,
+    /// Direction the Julia set morph is being animated in, 1 towards the Julia set or -1 back
+    julia_morph_direction: Option<f32>,
+    /// Point in the complex plane whose orbit is drawn, set by clicking
+    orbit_point: Option<[f64; 2]>,
+    /// Whether `colour_time` advances, animating colour expressions that use it
+    cycle_colours: bool,
+    /// Debug view showing the iteration count instead of the colour expression
+    greyscale_iterations: bool,
+    /// Frozen while colour cycling is off, so that screenshots match what is on screen
+    colour_time: f32,
+    probe_result: Option<ProbeResult>,
+    /// Settings at the time the view was refined, or `None` if it isn't currently refined
+    refined_settings: Option<UserSettings>,
     last_title_update: Option<Instant>,
     #[cfg(not(target_arch = "wasm32"))]
     clipboard: arboard::Clipboard,
+    /// Clipboard events for text fields, forwarded from the page
+    #[cfg(target_arch = "wasm32")]
+    clipboard_events: web::ClipboardEvents,
+    #[cfg(not(target_arch = "wasm32"))]
+    render_state: egui_wgpu::RenderState,
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_size: [u32; 2],
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_match_aspect: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    palette_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    animation: Animation,
+    /// Reloads the shader from disk when it changes, if a path to load it from was given
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_watcher: Option<ShaderWatcher>,
+    /// Created the first time the gallery is opened, as rendering the thumbnails takes a moment
+    #[cfg(not(target_arch = "wasm32"))]
+    preset_gallery: Option<PresetGallery>,
+    #[cfg(target_arch = "wasm32")]
+    webgl: bool,
+    #[cfg(target_arch = "wasm32")]
+    show_webgl_notice: bool,
 }
 
 impl FractalViewerApp {
+    /// Returns `None` if eframe has no wgpu renderer, e.g. because no compatible GPU was found, in
+    /// which case [`RendererUnavailableApp`] can be shown instead
     pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Option<Self> {
+        let mut shader_validator = ShaderValidator::new();
+
+        // Restore the view from the last session
         #[cfg(not(target_arch = "wasm32"))]
-        let settings = UserSettings::default();
+        let mut settings: UserSettings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            .unwrap_or_default();
         #[cfg(not(target_arch = "wasm32"))]
-        let import_error = None;
+        let mut import_error = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = shader_validator.validate_shader(&settings.shader_data) {
+            import_error = Some(format!(
+                "Could not restore the last session's equation or colour expression: {e}"
+            ));
+            settings = UserSettings::default();
+        }
 
+        // Settings in the URL take precedence over the last session's
         #[cfg(target_arch = "wasm32")]
-        let (mut settings, mut import_error) = match web_sys::window()
-            .and_then(|w| match w.location().href().ok() {
+        let (mut settings, mut import_error) =
+            match web_sys::window().and_then(|w| match w.location().href().ok() {
                 Some(s) if s.contains('?') => Some(s),
                 _ => None,
-            })
-            .map(|url| UserSettings::import_string(&url))
-            .unwrap_or_else(|| Ok(UserSettings::default()))
-        {
-            Ok(settings) => (settings, None),
-            Err(e) => (UserSettings::default(), Some(e.to_string())),
-        };
+            }) {
+                Some(url) => match UserSettings::import_string(&url) {
+                    Ok(settings) => (settings, None),
+                    Err(e) => (UserSettings::default(), Some(e.to_string())),
+                },
+                None => match web::load_settings().map(|s| UserSettings::import_string(&s)) {
+                    Some(Ok(settings)) => (settings, None),
+                    Some(Err(e)) => (
+                        UserSettings::default(),
+                        Some(format!(
+                            "Could not restore the last session's settings: {e}"
+                        )),
+                    ),
+                    None => (UserSettings::default(), None),
+                },
+            };
 
         #[cfg(target_arch = "wasm32")]
-        if let Err(e) = validate_shader(&settings.shader_data) {
+        if let Err(e) = shader_validator.validate_shader(&settings.shader_data) {
             import_error = Some(format!("Invalid equation or colour expression: {e}"));
             settings = UserSettings::default();
         }
 
-        let wgpu_render_state = cc.wgpu_render_state.as_ref()?;
-        let device = &wgpu_render_state.device;
-
-        let size = cc.egui_ctx.screen_rect().size();
-
-        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("fv_uniform_buffer"),
-            contents: bytemuck::cast_slice(&[Uniforms::new(size, &settings)]),
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        });
+        let colour_presets = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, COLOUR_PRESETS_KEY))
+            .unwrap_or_default();
 
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: Some("fv_uniform_bind_group_layout"),
-                entries: &[BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::VERTEX_FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-
-        let uniform_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("fv_uniform_bind_group"),
-            layout: &uniform_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
+        let open_sections = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, OPEN_SECTIONS_KEY))
+            .unwrap_or_default();
 
-        let renderer_state = RendererState {
-            device: Arc::clone(device),
-            target_format: wgpu_render_state.target_format.into(),
-            bind_group_layout: uniform_bind_group_layout,
-            bind_group: uniform_bind_group,
-            uniform_buffer,
-        };
+        let wgpu_render_state = cc.wgpu_render_state.as_ref()?;
 
-        let pipeline = renderer_state.generate_pipeline(&settings.shader_data);
+        let size = cc.egui_ctx.screen_rect().size();
 
+        let renderer_state = RendererState::new(
+            Arc::clone(&wgpu_render_state.device),
+            &wgpu_render_state.queue,
+            wgpu_render_state.target_format.into(),
+            Uniforms::new(size, &settings),
+        );
         wgpu_render_state
             .renderer
             .write()
             .callback_resources
-            .insert(FvRenderer {
-                pipeline,
-                state: renderer_state,
-            });
+            .insert(FvRenderer::new(
+                renderer_state,
+                shader_validator.shader(&settings.shader_data),
+                Some(cc.egui_ctx.clone()),
+            ));
 
         let adapter_info = wgpu_render_state.adapter.get_info();
-        let backend = match adapter_info.backend {
-            Backend::Empty => "Empty",
-            Backend::Vulkan => "Vulkan",
-            Backend::Metal => "Metal",
-            Backend::Dx12 => "DirectX 12",
-            Backend::Gl => "WebGL/OpenGL",
-            Backend::BrowserWebGpu => "WebGPU",
-        };
-        let driver_info = adapter_info.driver_info.clone();
+        let backend = backend_name(adapter_info.backend);
+        let driver_info = adapter_info.driver_info.trim().to_string();
+        let adapter_name = adapter_info.name.trim().to_string();
+        let device_limits = wgpu_render_state.device.limits();
+        // WebGL is only used on web if the browser doesn't support WebGPU
+        #[cfg(target_arch = "wasm32")]
+        let webgl = adapter_info.backend == Backend::Gl;
+
+        let format_features = wgpu_render_state
+            .adapter
+            .get_texture_format_features(wgpu_render_state.target_format);
+        // Without this feature, wgpu only allows the sample counts every GPU supports, 1 and 4,
+        // whatever the adapter reports
+        let adapter_specific = wgpu_render_state
+            .device
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let supported_sample_counts = MSAA_SAMPLE_COUNTS
+            .into_iter()
+            .filter(|&count| {
+                count == 1
+                    || ((count == 4 || adapter_specific)
+                        && format_features.flags.sample_count_supported(count)
+                        && format_features
+                            .flags
+                            .contains(TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE))
+            })
+            .collect();
+
+        let gpu_error = Arc::new(Mutex::new(None));
+        let device_lost = Arc::new(AtomicBool::new(false));
+        watch_device(
+            &wgpu_render_state.device,
+            &cc.egui_ctx,
+            &gpu_error,
+            &device_lost,
+        );
 
         Some(Self {
+            committed_settings: settings.clone(),
             settings,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            last_scroll: None,
+            last_interaction: None,
             last_frame: Instant::now(),
             prev_frame_time: Duration::from_secs(0),
             backend,
             driver_info,
+            adapter_name,
+            device_limits,
+            adapter_note: None,
+            supported_sample_counts,
+            msaa_samples: cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, MSAA_SAMPLES_KEY))
+                .unwrap_or(1),
+            gpu_error,
+            device_lost,
             show_ui: true,
+            auto_zoom: false,
+            auto_zoom_rate: AUTO_ZOOM_RATE,
+            auto_zoom_note: None,
+            zoom_text: String::new(),
+            zoom_text_error: None,
+            julia_follows_centre: false,
             recompile_shader: false,
             shader_error: None,
+            shader_validator,
             import_error,
+            confirm_reset: false,
+            bookmarks: bookmarks::load(),
+            bookmark_name: String::new(),
+            bookmark_error: None,
+            colour_presets,
+            colour_preset_name: String::new(),
+            open_sections,
+            colour_preset_import: String::new(),
+            colour_preset_error: None,
             fps_samples: VecDeque::new(),
+            fps_window: DEFAULT_FPS_WINDOW,
+            show_instant_fps: false,
+            in_background: false,
+            view_size: size,
+            painted_size: None,
+            hover_pos: None,
+            selection_start: None,
+            pan_velocity: Vec2::ZERO,
+            show_iteration_probe: false,
+            show_render_stats: false,
+            average_iterations: None,
+            show_julia_preview: false,
+            comparison: None,
+            show_orbit: false,
+            julia_morph_direction: None,
+            orbit_point: None,
+            cycle_colours: false,
+            greyscale_iterations: false,
+            colour_time: 0.0,
+            probe_result: None,
+            refined_settings: None,
             last_title_update: None,
             #[cfg(not(target_arch = "wasm32"))]
             clipboard: arboard::Clipboard::new().unwrap(),
+            #[cfg(target_arch = "wasm32")]
+            clipboard_events: web::ClipboardEvents::install(&cc.egui_ctx),
+            #[cfg(not(target_arch = "wasm32"))]
+            render_state: wgpu_render_state.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_size: [1920, 1080],
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_match_aspect: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            palette_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            animation: Animation::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_watcher: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            preset_gallery: None,
+            #[cfg(target_arch = "wasm32")]
+            webgl,
+            #[cfg(target_arch = "wasm32")]
+            show_webgl_notice: webgl,
         })
     }
 
+    /// Show a note about the adapter in the info window, e.g. if the requested backend wasn't
+    /// available
+    pub fn set_adapter_note(&mut self, note: String) {
+        self.adapter_note = Some(note);
+    }
+
+    /// Use the shader at `path` instead of the embedded one, reloading it whenever it changes
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_shader_path(&mut self, path: std::path::PathBuf) {
+        self.shader_watcher = Some(ShaderWatcher::new(path));
+    }
+
+    /// Import a settings export string or link, e.g. one passed on the command line
+    pub fn import_settings(&mut self, string: &str) -> Result<(), String> {
+        let settings = UserSettings::import_string(string).map_err(|e| e.to_string())?;
+        self.shader_validator
+            .validate_shader(&settings.shader_data)
+            .map_err(|e| format!("Invalid equation or colour expression: {e}"))?;
+        log::info!("Imported settings:\n{}", settings.describe());
+        self.set_settings(settings);
+        Ok(())
+    }
+
+    pub fn settings(&self) -> &UserSettings {
+        &self.settings
+    }
+
+    /// Replace all of the settings, recompiling the shader for the new equation and colour. An
+    /// invalid shader is reported in the UI rather than here.
+    pub fn set_settings(&mut self, settings: UserSettings) {
+        self.pan_velocity = Vec2::ZERO;
+        self.settings = settings;
+        self.recompile_shader = true;
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.settings.zoom
+    }
+
+    /// Set the zoom, clamped to the range the view supports
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if !zoom.is_nan() {
+            self.settings.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+    }
+
+    /// The point in the middle of the view, as the real and imaginary parts
+    pub fn centre(&self) -> [f64; 2] {
+        self.settings.centre
+    }
+
+    pub fn set_centre(&mut self, centre: [f64; 2]) {
+        if centre.iter().all(|x| x.is_finite()) {
+            self.pan_velocity = Vec2::ZERO;
+            self.settings.centre = centre;
+        }
+    }
+
+    /// The iteration limit set by the user, before any automatic increase with the zoom
+    pub fn iterations(&self) -> i32 {
+        self.settings.iterations
+    }
+
+    /// Set the iteration limit, clamped to at least 1
+    pub fn set_iterations(&mut self, iterations: i32) {
+        self.settings.iterations = iterations.clamp(1, MAX_ITERATIONS);
+    }
+
+    /// Adjust the zoom when the view changes size so that a pixel still covers the same distance,
+    /// rather than the region shown being stretched to fit the new size
+    fn keep_scale_on_resize(&mut self, size: Vec2) {
+        // Minimising the window makes the view empty, which shouldn't count as a resize
+        if size.min_elem() <= 0.0 {
+            return;
+        }
+        if let Some(previous) = self.painted_size.replace(size) {
+            if previous != size && self.settings.keep_scale_on_resize {
+                let factor = calculate_scale(size, &self.settings)
+                    / calculate_scale(previous, &self.settings);
+                self.settings.zoom *= factor;
+            }
+        }
+    }
+
     pub fn paint_fractal(&mut self, ui: &mut egui::Ui) {
         let size = ui.available_size();
         let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+        self.view_size = size;
+        self.keep_scale_on_resize(size);
+        self.hover_pos = response.hover_pos();
 
+        // The zoom can also be changed by the UI, keyboard and imports since the last frame
+        self.clamp_zoom();
         let scale = calculate_scale(size, &self.settings);
-        if response.dragged_by(PointerButton::Primary) {
+        // Two finger gestures on touchscreens; egui also reports the first finger as a pointer
+        // drag, so that must be ignored while a gesture is in progress
+        let multi_touch = ui
+            .input(|i| i.multi_touch())
+            .filter(|_| response.hovered() || response.dragged());
+        if response.drag_started_by(PointerButton::Primary) && ui.input(|i| i.modifiers.shift) {
+            self.selection_start = response.interact_pointer_pos();
+        }
+
+        let scroll = ui.input(|i| i.raw_scroll_delta);
+        // Any new interaction stops the view moving from an earlier drag
+        if response.is_pointer_button_down_on() || multi_touch.is_some() || scroll.y != 0.0 {
+            self.pan_velocity = Vec2::ZERO;
+        }
+        if self.settings.pan_inertia
+            && response.drag_stopped_by(PointerButton::Primary)
+            && self.selection_start.is_none()
+            && multi_touch.is_none()
+        {
+            self.pan_velocity = ui.input(|i| i.pointer.velocity());
+        }
+
+        if let Some(touch) = multi_touch {
+            self.settings.centre[0] -= touch.translation_delta.x as f64 * scale;
+            self.settings.centre[1] -= touch.translation_delta.y as f64 * scale;
+            if touch.zoom_delta != 1.0 {
+                self.last_scroll = Some(Instant::now());
+                // Touches move the pointer, so zoom around it, or where the pinch started
+                let anchor = ui
+                    .input(|i| i.pointer.interact_pos())
+                    .unwrap_or(touch.start_pos);
+                self.zoom_around(size, touch.zoom_delta as f64, Some(anchor));
+            }
+        } else if let Some(start) = self.selection_start {
+            if !response.dragged_by(PointerButton::Primary) {
+                self.selection_start = None;
+                if let Some(end) = response.interact_pointer_pos().or(response.hover_pos()) {
+                    self.zoom_to_selection(size, Rect::from_two_pos(start, end));
+                }
+            }
+        } else if response.dragged_by(PointerButton::Primary) {
             let drag_motion = response.drag_delta();
-            self.settings.centre[0] -= drag_motion.x * scale;
-            self.settings.centre[1] -= drag_motion.y * scale;
+            self.settings.centre[0] -= drag_motion.x as f64 * scale;
+            self.settings.centre[1] -= drag_motion.y as f64 * scale;
+        } else if response.double_clicked_by(PointerButton::Primary) {
+            if ui.input(|i| i.modifiers.command) {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    self.settings.centre = pointer_to_complex(pos, size, &self.settings);
+                }
+            } else {
+                self.settings.centre = [0.0, 0.0];
+                self.settings.zoom = 1.0;
+            }
+        } else if self.show_orbit && response.clicked_by(PointerButton::Primary) {
+            self.orbit_point = response
+                .interact_pointer_pos()
+                .map(|pos| pointer_to_complex(pos, size, &self.settings));
         } else if response.clicked_by(PointerButton::Secondary)
             || response.dragged_by(PointerButton::Secondary)
         {
             let pointer_pos = response.interact_pointer_pos().unwrap();
-            self.settings.initial_value[0] =
-                (pointer_pos.x - size.x / 2.0) * scale + self.settings.centre[0];
-            self.settings.initial_value[1] =
-                (pointer_pos.y - size.y / 2.0) * scale + self.settings.centre[1];
+            let value = pointer_to_complex(pointer_pos, size, &self.settings);
+            if (self.settings.julia_set || julia_morph(&self.settings) > 0.0)
+                && self.settings.mode == FractalMode::EscapeTime
+            {
+                self.settings.julia_c = value;
+            } else {
+                self.settings.initial_value = value;
+            }
         }
 
-        let scroll = ui.input(|i| i.raw_scroll_delta);
-        self.settings.zoom += self.settings.zoom * (scroll.y / 300.0).max(-0.9);
+        if self.settings.julia_set && self.julia_follows_centre {
+            self.settings.julia_c = self.settings.centre;
+        }
+
+        if scroll.y != 0.0 {
+            self.last_scroll = Some(Instant::now());
+            let direction = if self.settings.invert_scroll {
+                -1.0
+            } else {
+                1.0
+            };
+            let step = scroll.y as f64 * direction * self.settings.scroll_sensitivity as f64;
+            let factor = 1.0 + (step / SCROLL_ZOOM_DIVISOR).max(-0.9);
+            self.zoom_around(size, factor, response.hover_pos());
+        }
+
+        // Any interaction or settings change ends refinement
+        if self
+            .refined_settings
+            .as_ref()
+            .is_some_and(|refined| refined != &self.settings)
+        {
+            self.refined_settings = None;
+        }
 
-        let uniforms = Uniforms::new(size, &self.settings);
+        let panning = response.dragged() && self.selection_start.is_none();
+        if panning || scroll.y != 0.0 || multi_touch.is_some() {
+            self.last_interaction = Some(Instant::now());
+        }
+        let interacting = self
+            .last_interaction
+            .is_some_and(|i| i.elapsed() < INTERACTION_SETTLE_TIME);
+        if interacting {
+            ui.ctx().request_repaint_after(INTERACTION_SETTLE_TIME);
+        }
+
+        // Refining renders at full resolution with the most anti-aliasing, as well as with more
+        // iterations below
+        let refined = self.refined_settings.is_some().then(|| UserSettings {
+            render_scale: 1.0,
+            samples: REFINE_SAMPLES,
+            ..self.settings.clone()
+        });
+        let render_settings = refined.as_ref().unwrap_or(&self.settings);
+
+        // Only the render size is scaled; everything else works in terms of the logical size
+        let render_scale = render_settings.render_scale.clamp(MIN_RENDER_SCALE, 1.0);
+        // MSAA also has to render to a separate texture, as egui's render pass isn't multisampled
+        let sample_count = self.msaa_sample_count();
+        let render_size = (render_scale < 1.0 || sample_count > 1)
+            .then(|| (size * render_scale).max(Vec2::splat(1.0)).round());
+
+        // Only the iterations sent to the GPU change, never the value set by the user
+        let mut uniforms =
+            Uniforms::new(render_size.unwrap_or(size), render_settings).with_time(self.colour_time);
+        if self.greyscale_iterations {
+            uniforms = uniforms.with_flags(GREYSCALE_ITERATIONS);
+        }
+        if self.refined_settings.is_some() {
+            uniforms = uniforms.with_iterations(
+                effective_iterations(&self.settings).saturating_mul(REFINE_ITERATION_FACTOR),
+            );
+        } else if interacting {
+            uniforms = uniforms.with_iterations(
+                (effective_iterations(&self.settings) / INTERACTION_ITERATION_DIVISOR).max(1),
+            );
+        }
 
         let callback = FvRenderCallback {
             uniforms,
-            shader_recompilation_options: if self.recompile_shader {
+            render_size: render_size.map(|size| [size.x as u32, size.y as u32]),
+            shader_source: if self.recompile_shader {
                 self.recompile_shader = false;
-                Some(self.settings.shader_data.clone())
+                Some(self.shader_validator.shader(&self.settings.shader_data))
             } else {
                 None
             },
+            palette: self.settings.palette.clone(),
+            sample_count,
         };
 
         ui.painter()
             .add(egui_wgpu::Callback::new_paint_callback(rect, callback));
+
+        self.paint_comparison(ui, rect);
+        if self.settings.show_grid {
+            self.paint_grid(ui, rect);
+        }
+        if self.show_orbit {
+            self.paint_orbit(ui, rect);
+        }
+        self.paint_selection(ui);
+        self.paint_julia_preview(ui, rect);
+        self.paint_coordinate_readout(ui, rect);
     }
-}
 
-impl eframe::App for FractalViewerApp {
-    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
-        let fps = self.fps_samples.iter().sum::<f32>() / self.fps_samples.len() as f32;
-        if self.last_title_update.is_none()
-            || self
-                .last_title_update
-                .is_some_and(|i| i.elapsed() >= Duration::from_secs(1))
-        {
-            let title = format!(
-                "{} {} [{} | {} | {:.0} FPS]",
-                env!("CARGO_PKG_NAME"),
-                env!("CARGO_PKG_VERSION"),
-                self.backend,
-                std::env::consts::ARCH,
-                fps
-            );
+    /// Draw the real and imaginary axes, and grid lines at a power of ten spacing suited to the zoom
+    fn paint_grid(&self, ui: &mut egui::Ui, rect: Rect) {
+        let size = self.view_size;
+        let scale = calculate_scale(size, &self.settings);
+        let spacing = 10f64.powf((GRID_TARGET_SPACING * scale).log10().round());
+        if !spacing.is_finite() || spacing <= 0.0 {
+            return;
+        }
+        let decimals = (-spacing.log10()).max(0.0) as usize;
+        let [min_re, min_im] = pointer_to_complex(rect.min, size, &self.settings);
+        let [max_re, max_im] = pointer_to_complex(rect.max, size, &self.settings);
 
-            #[cfg(not(target_arch = "wasm32"))]
-            ctx.send_viewport_cmd(ViewportCommand::Title(title));
+        let painter = ui.painter();
+        let grid_stroke = egui::Stroke::new(1.0, Color32::from_white_alpha(60));
+        let axis_stroke = egui::Stroke::new(1.5, Color32::from_white_alpha(200));
+        let font = egui::FontId::monospace(11.0);
+        // Labels follow the axes, staying at the edge of the view when an axis is off screen
+        let origin = complex_to_pointer([0.0, 0.0], size, &self.settings);
+        let label_x = origin.x.min(rect.right() - 60.0).max(rect.left() + 2.0);
+        let label_y = origin.y.min(rect.bottom() - 14.0).max(rect.top() + 2.0);
 
-            #[cfg(target_arch = "wasm32")]
-            if let Some(title_element) = web_sys::window()
-                .and_then(|window| window.document())
-                .and_then(|document| document.get_element_by_id("title"))
-            {
-                title_element.set_inner_html(&title);
+        // The number of lines is bounded in case the spacing is too fine for f64 to step through
+        let start = (min_re / spacing).ceil();
+        for i in 0..MAX_GRID_LINES {
+            // Adding 0.0 turns -0.0 into 0.0, so the label doesn't show a minus sign
+            let re = (start + i as f64) * spacing + 0.0;
+            if re > max_re {
+                break;
             }
-
-            self.last_title_update = Some(Instant::now());
+            let x = complex_to_pointer([re, 0.0], size, &self.settings).x;
+            let stroke = if re == 0.0 { axis_stroke } else { grid_stroke };
+            painter.vline(x, rect.y_range(), stroke);
+            painter.text(
+                Pos2::new(x + 2.0, label_y),
+                egui::Align2::LEFT_TOP,
+                format!("{re:.decimals$}"),
+                font.clone(),
+                Color32::WHITE,
+            );
         }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        if ctx.input(|i| i.key_pressed(Key::F11)) {
-            let current_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap());
-            ctx.send_viewport_cmd(ViewportCommand::Fullscreen(!current_fullscreen));
+        let start = (min_im / spacing).ceil();
+        for i in 0..MAX_GRID_LINES {
+            let im = (start + i as f64) * spacing + 0.0;
+            if im > max_im {
+                break;
+            }
+            let y = complex_to_pointer([0.0, im], size, &self.settings).y;
+            let stroke = if im == 0.0 { axis_stroke } else { grid_stroke };
+            painter.hline(rect.x_range(), y, stroke);
+            if im != 0.0 {
+                painter.text(
+                    Pos2::new(label_x + 2.0, y - 2.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("{im:.decimals$}i"),
+                    font.clone(),
+                    Color32::WHITE,
+                );
+            }
+        }
+    }
+
+    /// Draw the orbit of the clicked point as a line through each value of z
+    fn paint_orbit(&self, ui: &mut egui::Ui, rect: Rect) {
+        let Some(points) = self
+            .orbit_point
+            .and_then(|point| orbit::orbit(&self.settings, point))
+        else {
+            return;
+        };
+
+        let points: Vec<Pos2> = points
+            .into_iter()
+            .map(|z| complex_to_pointer(z, self.view_size, &self.settings))
+            .collect();
+        let painter = ui.painter_at(rect);
+        painter.add(egui::Shape::line(
+            points.clone(),
+            egui::Stroke::new(1.0, Color32::from_rgb(255, 220, 0)),
+        ));
+        for point in points {
+            painter.circle_filled(point, 2.0, Color32::from_rgb(255, 220, 0));
+        }
+    }
+
+    /// Show the coordinate under the cursor and the zoom level in the corner of the view
+    fn paint_coordinate_readout(&self, ui: &mut egui::Ui, rect: Rect) {
+        let Some(pointer_pos) = self.hover_pos else {
+            return;
+        };
+
+        let scale = calculate_scale(self.view_size, &self.settings);
+        let [re, im] = pointer_to_complex(pointer_pos, self.view_size, &self.settings);
+        // Enough decimal places to tell neighbouring pixels apart
+        let decimals = (-scale.log10()).ceil().clamp(0.0, 20.0) as usize + 1;
+        let text = format!(
+            "{re:.decimals$} {} {:.decimals$}i\nZoom: {:.3e}",
+            if im < 0.0 { '-' } else { '+' },
+            im.abs(),
+            self.settings.zoom
+        );
+
+        let painter = ui.painter();
+        let galley = painter.layout_no_wrap(text, egui::FontId::monospace(12.0), Color32::WHITE);
+        let margin = Vec2::splat(4.0);
+        let text_pos = rect.right_bottom() - galley.size() - margin * 2.0;
+        painter.rect_filled(
+            Rect::from_min_size(text_pos - margin, galley.size() + margin * 2.0),
+            4.0,
+            Color32::from_black_alpha(160),
+        );
+        painter.galley(text_pos, galley, Color32::WHITE);
+    }
+
+    /// Zoom so that the selected part of the view fills it, keeping the aspect ratio
+    fn zoom_to_selection(&mut self, size: Vec2, selection: Rect) {
+        self.settings.centre = pointer_to_complex(selection.center(), size, &self.settings);
+        let selection_size = selection.size().max(Vec2::splat(MIN_SELECTION_SIZE));
+        let factor = (size / selection_size).min_elem();
+        self.settings.zoom *= factor as f64;
+        self.clamp_zoom();
+    }
+
+    /// Outline the area being selected to zoom into
+    fn paint_selection(&self, ui: &mut egui::Ui) {
+        if let (Some(start), Some(end)) = (self.selection_start, self.hover_pos) {
+            ui.painter().rect_stroke(
+                Rect::from_two_pos(start, end),
+                0.0,
+                egui::Stroke::new(1.0, Color32::WHITE),
+            );
+        }
+    }
+
+    fn clamp_zoom(&mut self) {
+        self.settings.zoom = if self.settings.zoom.is_nan() {
+            1.0
+        } else {
+            self.settings.zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+        };
+    }
+
+    /// Multiply the zoom by `factor`, keeping the point at `pos` (if any) fixed on screen
+    fn zoom_around(&mut self, size: Vec2, factor: f64, pos: Option<Pos2>) {
+        let before = pos.map(|pos| pointer_to_complex(pos, size, &self.settings));
+        self.settings.zoom *= factor;
+        self.clamp_zoom();
+
+        if let (Some(pos), Some(before)) = (pos, before) {
+            let after = pointer_to_complex(pos, size, &self.settings);
+            self.settings.centre[0] += before[0] - after[0];
+            self.settings.centre[1] += before[1] - after[1];
+        }
+    }
+
+    /// Draw a small Julia set for the point under the cursor in the corner of the view
+    fn paint_julia_preview(&self, ui: &mut egui::Ui, rect: Rect) {
+        if !self.show_julia_preview
+            || self.settings.julia_set
+            || self.settings.mode != FractalMode::EscapeTime
+        {
+            return;
+        }
+        let Some(pointer_pos) = self.hover_pos else {
+            return;
+        };
+
+        let preview_settings = UserSettings {
+            julia_set: true,
+            julia_c: pointer_to_complex(pointer_pos, self.view_size, &self.settings),
+            centre: [0.0, 0.0],
+            zoom: 1.0,
+            iterations: self.settings.iterations.min(PREVIEW_MAX_ITERATIONS),
+            high_precision: false,
+            samples: 1,
+            ..self.settings.clone()
+        };
+
+        let preview_rect = Rect::from_min_size(
+            rect.left_bottom() - egui::vec2(0.0, PREVIEW_SIZE),
+            Vec2::splat(PREVIEW_SIZE),
+        );
+        // The shader works in physical pixels relative to the whole window, not to the callback
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let uniforms = Uniforms::new(preview_rect.size() * pixels_per_point, &preview_settings)
+            .offset(-preview_rect.min.to_vec2() * pixels_per_point)
+            .with_time(self.colour_time);
+
+        ui.painter().add(egui_wgpu::Callback::new_paint_callback(
+            preview_rect,
+            JuliaPreviewCallback { uniforms },
+        ));
+        ui.painter()
+            .rect_stroke(preview_rect, 0.0, ui.visuals().window_stroke);
+    }
+
+    /// Draw the compared settings to the right of the divider, and let the divider be dragged
+    fn paint_comparison(&mut self, ui: &mut egui::Ui, rect: Rect) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+
+        let handle_x = rect.left() + rect.width() * comparison.divider;
+        let handle = Rect::from_x_y_ranges(
+            handle_x - COMPARISON_HANDLE_WIDTH / 2.0..=handle_x + COMPARISON_HANDLE_WIDTH / 2.0,
+            rect.y_range(),
+        );
+        let response = ui
+            .interact(
+                handle,
+                ui.id().with("comparison_divider"),
+                egui::Sense::drag(),
+            )
+            .on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+        if let Some(pos) = response
+            .interact_pointer_pos()
+            .filter(|_| response.dragged())
+        {
+            comparison.divider = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        }
+        let divider_x = rect.left() + rect.width() * comparison.divider;
+        let right = Rect::from_min_max(egui::pos2(divider_x, rect.top()), rect.max);
+
+        match &comparison.error {
+            None => {
+                let settings = comparison.settings_for_view(&self.settings);
+                // Drawn over the whole view like the main callback, so that the shader's pixel
+                // coordinates line up, but clipped to the right of the divider
+                let callback = ComparisonCallback {
+                    uniforms: Uniforms::new(self.view_size, &settings).with_time(self.colour_time),
+                    shader_data: settings.shader_data,
+                    template: Arc::clone(&self.shader_validator.template),
+                    palette: settings.palette,
+                };
+                ui.painter()
+                    .with_clip_rect(right)
+                    .add(egui_wgpu::Callback::new_paint_callback(rect, callback));
+            }
+            Some(e) => {
+                ui.painter().rect_filled(right, 0.0, Color32::BLACK);
+                ui.painter().with_clip_rect(right).text(
+                    right.left_center() + egui::vec2(8.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("{}: {e}", e.kind()),
+                    egui::FontId::monospace(12.0),
+                    Color32::RED,
+                );
+            }
+        }
+
+        let stroke = ui.visuals().window_stroke;
+        ui.painter().vline(divider_x, rect.y_range(), stroke);
+        for (text, pos, align) in [
+            (
+                "A",
+                egui::pos2(divider_x - 8.0, rect.top() + 8.0),
+                egui::Align2::RIGHT_TOP,
+            ),
+            (
+                "B",
+                egui::pos2(divider_x + 8.0, rect.top() + 8.0),
+                egui::Align2::LEFT_TOP,
+            ),
+        ] {
+            ui.painter().text(
+                pos,
+                align,
+                text,
+                egui::FontId::proportional(16.0),
+                Color32::WHITE,
+            );
+        }
+    }
+
+    /// Start comparing against a copy of the current settings, which can then be changed
+    fn start_comparison(&mut self) {
+        self.comparison = Some(Comparison {
+            settings: self.settings.clone(),
+            divider: 0.5,
+            error: self
+                .shader_validator
+                .validate_shader(&self.settings.shader_data)
+                .err(),
+        });
+    }
+
+    /// Swap the compared settings with the main ones, so that the other side can be edited. The
+    /// view stays where it is.
+    fn swap_comparison(&mut self) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+        let main = std::mem::replace(
+            &mut self.settings,
+            comparison.settings_for_view(&comparison.settings),
+        );
+        self.settings.centre = main.centre;
+        self.settings.zoom = main.zoom;
+        self.settings.framing = main.framing;
+        comparison.error = self
+            .shader_validator
+            .validate_shader(&main.shader_data)
+            .err();
+        comparison.settings = main;
+        self.recompile_shader = true;
+    }
+
+    /// Add the current settings to the undo history if they have changed
+    fn commit_settings(&mut self) {
+        if self.settings != self.committed_settings {
+            let previous = std::mem::replace(&mut self.committed_settings, self.settings.clone());
+            self.undo_stack.push_back(previous);
+            if self.undo_stack.len() > UNDO_HISTORY_LENGTH {
+                self.undo_stack.pop_front();
+            }
+            self.redo_stack.clear();
+        }
+    }
+
+    /// Commit the settings once the user has finished interacting, so that e.g. a whole drag or
+    /// scroll only creates one history entry
+    fn update_undo_history(&mut self, ctx: &Context) {
+        let scrolled_recently = self
+            .last_scroll
+            .is_some_and(|i| i.elapsed() < UNDO_SETTLE_TIME);
+        let interacting = ctx.input(|i| i.pointer.any_down() || !i.keys_down.is_empty())
+            || ctx.wants_keyboard_input()
+            || self.pan_velocity != Vec2::ZERO
+            || scrolled_recently;
+
+        if scrolled_recently {
+            ctx.request_repaint_after(UNDO_SETTLE_TIME);
+        }
+
+        if !interacting {
+            self.commit_settings();
+        }
+    }
+
+    fn restore_settings(&mut self, settings: UserSettings) -> UserSettings {
+        self.pan_velocity = Vec2::ZERO;
+        if settings.shader_data != self.settings.shader_data {
+            self.recompile_shader = true;
+        }
+        self.committed_settings = settings.clone();
+        std::mem::replace(&mut self.settings, settings)
+    }
+
+    fn undo(&mut self) {
+        self.commit_settings();
+        if let Some(previous) = self.undo_stack.pop_back() {
+            let current = self.restore_settings(previous);
+            self.redo_stack.push(current);
+        }
+    }
+
+    fn redo(&mut self) {
+        self.commit_settings();
+        if let Some(next) = self.redo_stack.pop() {
+            let current = self.restore_settings(next);
+            self.undo_stack.push_back(current);
+        }
+    }
+
+    /// Reset all settings to their defaults, asking first if there is a custom shader to lose
+    fn request_reset_all(&mut self) {
+        if self.settings.shader_data == CustomShaderData::default() {
+            self.reset_all();
+        } else {
+            self.confirm_reset = true;
+        }
+    }
+
+    fn reset_all(&mut self) {
+        // Keep the current settings in the undo history
+        self.commit_settings();
+        self.settings = UserSettings::default();
+        self.pan_velocity = Vec2::ZERO;
+        self.recompile_shader = true;
+        self.confirm_reset = false;
+    }
+
+    fn handle_keyboard_navigation(&mut self, ctx: &Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let (pan, zoom) = ctx.input(|i| {
+            let mut pan = Vec2::ZERO;
+            if i.key_down(Key::ArrowLeft) {
+                pan.x -= 1.0;
+            }
+            if i.key_down(Key::ArrowRight) {
+                pan.x += 1.0;
+            }
+            if i.key_down(Key::ArrowUp) {
+                pan.y -= 1.0;
+            }
+            if i.key_down(Key::ArrowDown) {
+                pan.y += 1.0;
+            }
+
+            let mut zoom = 0.0;
+            if i.key_down(Key::Plus) || i.key_down(Key::Equals) || i.key_down(Key::PageUp) {
+                zoom += 1.0;
+            }
+            if i.key_down(Key::Minus) || i.key_down(Key::PageDown) {
+                zoom -= 1.0;
+            }
+            (pan, zoom)
+        });
+
+        if pan == Vec2::ZERO && zoom == 0.0 {
+            return;
+        }
+        self.pan_velocity = Vec2::ZERO;
+
+        // Avoid a big jump on the first frame after the app has been idle
+        let dt = self.prev_frame_time.as_secs_f64().min(0.1);
+        let scale = calculate_scale(self.view_size, &self.settings);
+        self.settings.centre[0] += pan.x as f64 * KEYBOARD_PAN_SPEED * dt * scale;
+        self.settings.centre[1] += pan.y as f64 * KEYBOARD_PAN_SPEED * dt * scale;
+        self.settings.zoom *= KEYBOARD_ZOOM_RATE.powf(zoom * dt);
+
+        // Keep moving while the keys are held down
+        self.request_animation_frame(ctx);
+    }
+
+    /// Keep the view moving after a drag is released, slowing down exponentially
+    fn update_pan_inertia(&mut self, ctx: &Context) {
+        if self.pan_velocity == Vec2::ZERO {
+            return;
+        }
+        if !self.settings.pan_inertia {
+            self.pan_velocity = Vec2::ZERO;
+            return;
+        }
+
+        let dt = self.prev_frame_time.as_secs_f32().min(0.1);
+        let scale = calculate_scale(self.view_size, &self.settings);
+        self.settings.centre[0] -= (self.pan_velocity.x * dt) as f64 * scale;
+        self.settings.centre[1] -= (self.pan_velocity.y * dt) as f64 * scale;
+        self.pan_velocity *= (-dt / PAN_INERTIA_DECAY_TIME).exp();
+
+        if self.pan_velocity.length() < PAN_INERTIA_MIN_SPEED {
+            self.pan_velocity = Vec2::ZERO;
+        } else {
+            // Render at reduced quality while moving, as when dragging
+            self.last_interaction = Some(Instant::now());
+            self.request_animation_frame(ctx);
+        }
+    }
+
+    fn update_julia_morph(&mut self, ctx: &Context) {
+        let Some(direction) = self.julia_morph_direction else {
+            return;
+        };
+        let step = direction * self.prev_frame_time.as_secs_f32().min(0.1) / JULIA_MORPH_DURATION;
+        self.settings.julia_morph = (self.settings.julia_morph + step).clamp(0.0, 1.0);
+        if self.settings.julia_morph == 0.0 || self.settings.julia_morph == 1.0 {
+            self.julia_morph_direction = None;
+        } else {
+            self.request_animation_frame(ctx);
+        }
+    }
+
+    fn average_fps(&self) -> f32 {
+        if self.fps_samples.is_empty() {
+            return 0.0;
+        }
+        self.fps_samples.iter().sum::<f32>() / self.fps_samples.len() as f32
+    }
+
+    /// The FPS to show, either for the last frame or averaged over the last `fps_window` frames
+    fn displayed_fps(&self) -> f32 {
+        if self.show_instant_fps {
+            self.fps_samples.back().copied().unwrap_or(0.0)
+        } else {
+            self.average_fps()
+        }
+    }
+
+    /// The MSAA sample count to render the view with, which is 1 if the GPU doesn't support the
+    /// one in the settings
+    fn msaa_sample_count(&self) -> u32 {
+        if self.supported_sample_counts.contains(&self.msaa_samples) {
+            self.msaa_samples
+        } else {
+            1
+        }
+    }
+
+    /// Keep zooming into the centre of the view, until the shader runs out of precision
+    fn update_auto_zoom(&mut self, ctx: &Context) {
+        if !self.auto_zoom {
+            return;
+        }
+
+        if precision_exhausted(self.view_size, &self.settings) {
+            self.auto_zoom = false;
+            let note = format!(
+                "Auto zoom stopped at zoom {:.3e}: the limit of precision has been reached",
+                self.settings.zoom
+            );
+            log::info!("{note}");
+            self.auto_zoom_note = Some(note);
+            return;
+        }
+
+        self.settings.zoom *= self
+            .auto_zoom_rate
+            .powf(self.prev_frame_time.as_secs_f64().min(0.1));
+        self.request_animation_frame(ctx);
+    }
+
+    /// Request the next frame of a continuous effect, respecting the frame rate limit
+    fn request_animation_frame(&self, ctx: &Context) {
+        if self.in_background {
+            ctx.request_repaint_after(BACKGROUND_FRAME_INTERVAL);
+            return;
+        }
+        match self.settings.frame_rate_limit.frame_interval() {
+            Some(interval) => {
+                ctx.request_repaint_after(interval.saturating_sub(self.last_frame.elapsed()))
+            }
+            None => ctx.request_repaint(),
+        }
+    }
+
+    /// Render the current view at the given size and encode it as a PNG image. The image shows
+    /// the same region as the on-screen view as long as its aspect ratio matches. Fails if the GPU
+    /// runs out of memory or the device is lost.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_to_image(&self, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        self.render_settings_to_image(&self.settings, width, height)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_settings_to_image(
+        &self,
+        settings: &UserSettings,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, String> {
+        let mut renderer = self.render_state.renderer.write();
+        let fv_renderer: &mut FvRenderer = renderer.callback_resources.get_mut().unwrap();
+        fv_renderer
+            .state
+            .set_palette(&self.render_state.queue, &settings.palette);
+
+        let uniforms = Uniforms::new(Vec2::new(width as f32, height as f32), settings)
+            .with_time(self.colour_time);
+        let pixels =
+            fv_renderer.render_to_rgba(&self.render_state.queue, uniforms, width, height)?;
+
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_data, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            // Embed the settings so the view can be opened again from the image
+            encoder
+                .add_text_chunk(PNG_SETTINGS_KEYWORD.to_string(), settings.export_string())
+                .unwrap();
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&pixels).unwrap();
+        }
+        Ok(png_data)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_palette(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| decode_palette_png(&data))
+        {
+            Ok(palette) => {
+                self.settings.palette = palette;
+                self.settings.use_palette = true;
+                self.palette_error = None;
+            }
+            Err(e) => self.palette_error = Some(e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_screenshot(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .set_file_name(self.settings.file_name("png"))
+            .save_file()
+        else {
+            return;
+        };
+
+        let [width, height] = self.screenshot_size;
+        self.screenshot_error = self
+            .render_to_image(width, height)
+            .and_then(|png_data| std::fs::write(path, png_data).map_err(|e| e.to_string()))
+            .err();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_recording(&mut self) {
+        let animation = &mut self.animation;
+        // Frames are rendered with the current pipeline, so the shader can't change between them
+        if [&animation.start, &animation.end]
+            .into_iter()
+            .flatten()
+            .any(|settings| settings.shader_data != self.settings.shader_data)
+        {
+            animation.error = Some(
+                "The start and end views must use the current equation and colour".to_string(),
+            );
+            return;
+        }
+
+        if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+            animation.error = None;
+            animation.recording = Some(Recording { dir, next_frame: 0 });
+        }
+    }
+
+    /// Render the next frame of the animation being recorded, if any. Only one frame is rendered
+    /// per update so that the UI can show progress and the recording can be cancelled.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn record_animation_frame(&mut self, ctx: &Context) {
+        let Some(recording) = &self.animation.recording else {
+            return;
+        };
+        let frame = recording.next_frame;
+        let (Some(settings), Some(path)) = (
+            self.animation.frame_settings(frame),
+            self.animation.frame_path(frame),
+        ) else {
+            self.animation.recording = None;
+            return;
+        };
+
+        let [width, height] = self.screenshot_size;
+        if let Err(e) = self
+            .render_settings_to_image(&settings, width, height)
+            .and_then(|png_data| std::fs::write(path, png_data).map_err(|e| e.to_string()))
+        {
+            self.animation.error = Some(e);
+            self.animation.recording = None;
+            return;
+        }
+
+        let recording = self.animation.recording.as_mut().unwrap();
+        recording.next_frame += 1;
+        if recording.next_frame >= self.animation.frames {
+            self.animation.recording = None;
+        }
+        ctx.request_repaint();
+    }
+
+    /// Read back the iteration counts from the previous frame's statistics render and start
+    /// another. The view is rendered at a lower resolution, so the average is approximate.
+    fn update_render_stats(&mut self, ctx: &Context, frame: &Frame) {
+        let Some(render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let mut renderer = render_state.renderer.write();
+        let fv_renderer: &mut FvRenderer = renderer.callback_resources.get_mut().unwrap();
+
+        if let Some(results) = fv_renderer.poll_stats() {
+            let total: u64 = results.iter().map(|r| r.iterations as u64).sum();
+            self.average_iterations = Some(total as f32 / results.len().max(1) as f32);
+        }
+
+        let stats_size = (self.view_size * (STATS_RESOLUTION / self.view_size.max_elem()))
+            .round()
+            .max(Vec2::splat(1.0));
+        let uniforms =
+            Uniforms::new(stats_size, &self.settings).with_flags(ITERATION_PROBE | ITERATION_STATS);
+        fv_renderer.start_stats(
+            &render_state.queue,
+            uniforms,
+            [stats_size.x as u32, stats_size.y as u32],
+        );
+        // Keep repainting until the result has been read back
+        if self.settings.frame_rate_limit != FrameRateLimit::OnDemand {
+            self.request_animation_frame(ctx);
+        }
+    }
+
+    /// Read back the result of the previous iteration probe and start a new one at the hovered
+    /// position.
+    fn update_iteration_probe(&mut self, ctx: &Context, frame: &Frame) {
+        let Some(render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let mut renderer = render_state.renderer.write();
+        let fv_renderer: &mut FvRenderer = renderer.callback_resources.get_mut().unwrap();
+
+        if let Some(result) = fv_renderer.poll_probe() {
+            self.probe_result = Some(result);
+        }
+
+        match self.hover_pos {
+            Some(pos) => {
+                let uniforms = Uniforms::new(self.view_size, &self.settings)
+                    .offset(pos.to_vec2() - Vec2::splat(0.5))
+                    .with_flags(ITERATION_PROBE);
+                fv_renderer.start_probe(&render_state.queue, uniforms);
+                // Keep repainting until the result has been read back. In on demand mode it is
+                // only read back on the next input instead.
+                if self.settings.frame_rate_limit != FrameRateLimit::OnDemand {
+                    self.request_animation_frame(ctx);
+                }
+            }
+            None => self.probe_result = None,
+        }
+    }
+}
+
+impl eframe::App for FractalViewerApp {
+    #[cfg(target_arch = "wasm32")]
+    fn raw_input_hook(&mut self, _ctx: &Context, raw_input: &mut egui::RawInput) {
+        self.clipboard_events.drain_into(&mut raw_input.events);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, COLOUR_PRESETS_KEY, &self.colour_presets);
+        eframe::set_value(storage, OPEN_SECTIONS_KEY, &self.open_sections);
+        eframe::set_value(storage, MSAA_SAMPLES_KEY, &self.msaa_samples);
+        #[cfg(not(target_arch = "wasm32"))]
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+        // Stored as an export string so that a value from an older version can be migrated, or
+        // reported as an import error if it can't be
+        #[cfg(target_arch = "wasm32")]
+        web::save_settings(&self.settings);
+    }
+
+    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        // Record the frame time first, so there is always at least one sample to average
+        self.prev_frame_time = self.last_frame.elapsed();
+        self.last_frame = Instant::now();
+        // Frames in the background, and the first one after, are slow on purpose
+        let was_in_background = self.in_background;
+        self.in_background = is_in_background(ctx);
+        if !self.in_background && !was_in_background {
+            let new_fps = self.prev_frame_time.as_secs_f32().recip();
+            self.fps_samples.push_back(new_fps);
+        }
+        // The window can also have been made smaller since the last frame
+        while self.fps_samples.len() > self.fps_window {
+            self.fps_samples.pop_front();
+        }
+
+        let fps = self.displayed_fps();
+        if self.last_title_update.is_none()
+            || self
+                .last_title_update
+                .is_some_and(|i| i.elapsed() >= Duration::from_secs(1))
+        {
+            let title = format!(
+                "{} {} [{} | {} | {:.0} FPS]",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                self.backend,
+                std::env::consts::ARCH,
+                fps
+            );
+
+            #[cfg(not(target_arch = "wasm32"))]
+            ctx.send_viewport_cmd(ViewportCommand::Title(title));
+
+            #[cfg(target_arch = "wasm32")]
+            if let Some(title_element) = web_sys::window()
+                .and_then(|window| window.document())
+                .and_then(|document| document.get_element_by_id("title"))
+            {
+                title_element.set_inner_html(&title);
+            }
+
+            self.last_title_update = Some(Instant::now());
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if ctx.input(|i| i.key_pressed(Key::F11)) {
+            let current_fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap());
+            ctx.send_viewport_cmd(ViewportCommand::Fullscreen(!current_fullscreen));
         }
 
         if ctx.input(|i| i.key_pressed(Key::F1)) {
             self.show_ui = !self.show_ui;
         }
 
-        egui::CentralPanel::default()
-            .frame(egui::Frame::default().inner_margin(0.0))
-            .show(ctx, |ui| self.paint_fractal(ui));
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = ctx.input_mut(|i| {
+                let redo = i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+                    || i.consume_key(Modifiers::COMMAND, Key::Y);
+                let undo = i.consume_key(Modifiers::COMMAND, Key::Z);
+                (undo, redo)
+            });
+            if undo {
+                self.undo();
+            } else if redo {
+                self.redo();
+            }
+
+            if ctx.input_mut(|i| i.consume_key(RESET_MODIFIERS, Key::R)) {
+                self.request_reset_all();
+            }
+        }
+
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::Space)) {
+            self.auto_zoom = !self.auto_zoom;
+        }
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::G)) {
+            self.settings.show_grid = !self.settings.show_grid;
+        }
+
+        self.handle_keyboard_navigation(ctx);
+        self.update_pan_inertia(ctx);
+        self.update_julia_morph(ctx);
+        self.update_auto_zoom(ctx);
+        if self.cycle_colours {
+            self.colour_time += self.prev_frame_time.as_secs_f32().min(0.1);
+            self.request_animation_frame(ctx);
+        }
+
+        let device_lost = self.device_lost.load(Ordering::Relaxed);
+        if device_lost {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("The GPU device was lost");
+                    ui.label("This can happen when the graphics driver restarts, the GPU is switched, or the browser stops a slow frame.");
+                    // eframe owns the device and can't be given a new one, so the app has to start again
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.label("Restart the app to keep drawing. Your settings will be restored.");
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        ui.label("Reload the page to keep drawing. Your settings will be restored.");
+                        if ui.button("Reload").clicked() {
+                            if let Some(window) = web_sys::window() {
+                                let _ = window.location().reload();
+                            }
+                        }
+                    }
+                });
+            });
+        } else {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::default().inner_margin(0.0))
+                .show(ctx, |ui| self.paint_fractal(ui));
+
+            if self.show_iteration_probe {
+                self.update_iteration_probe(ctx, frame);
+            }
+            if self.show_render_stats {
+                self.update_render_stats(ctx, frame);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut save_screenshot = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut start_recording = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut load_palette = false;
+        let mut start_comparison = false;
+        let mut swap_comparison = false;
+        let mut undo = false;
+        let mut redo = false;
+        let mut reset_all = false;
+        // Taken out of self so that sections' contents can still borrow all of it
+        let mut sections = std::mem::take(&mut self.open_sections);
 
         egui::Window::new(env!("CARGO_PKG_NAME"))
             .title_bar(true)
@@ -267,37 +1907,192 @@ impl eframe::App for FractalViewerApp {
                     std::env::consts::ARCH
                 ));
 
+                let limits = format!(
+                    "Max texture size: {}px\nMax buffer size: {} MiB",
+                    self.device_limits.max_texture_dimension_2d,
+                    self.device_limits.max_buffer_size / (1024 * 1024),
+                );
                 if self.driver_info.is_empty() {
-                    ui.label(format!("Render backend: {}", self.backend));
+                    ui.label(format!("Render backend: {}", self.backend)).on_hover_text(limits);
                 } else {
-                    ui.label(format!("Render backend: {} ({})", self.backend, &self.driver_info));
+                    ui.label(format!("Render backend: {} ({})", self.backend, &self.driver_info)).on_hover_text(limits);
+                }
+                if !self.adapter_name.is_empty() {
+                    ui.label(format!("GPU: {}", self.adapter_name));
+                }
+                if let Some(note) = &self.adapter_note {
+                    ui.colored_label(Color32::YELLOW, note);
+                }
+                if let Some(error) = self.gpu_error.lock().unwrap().as_ref() {
+                    ui.colored_label(Color32::RED, error);
+                }
+                #[cfg(target_arch = "wasm32")]
+                if self.webgl && effective_iterations(&self.settings) > WEBGL_SAFE_ITERATIONS {
+                    ui.colored_label(
+                        Color32::YELLOW,
+                        format!("More than {WEBGL_SAFE_ITERATIONS} iterations may hit the browser's shader time limit on WebGL, which makes the view go black"),
+                    );
                 }
 
                 ui.label(format!(
-                    "Last frame: {:.1}ms (smoothed FPS: {:.0})",
+                    "Last frame: {:.1}ms ({} FPS: {:.0})",
                     self.prev_frame_time.as_micros() as f64 / 1000.0,
-                    self.fps_samples.iter().sum::<f32>() / self.fps_samples.len() as f32
+                    if self.show_instant_fps { "instantaneous" } else { "smoothed" },
+                    fps
                 ));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_instant_fps, "Instantaneous FPS");
+                    ui.add_enabled(
+                        !self.show_instant_fps,
+                        egui::Slider::new(&mut self.fps_window, 1..=MAX_FPS_WINDOW)
+                            .logarithmic(true)
+                            .text("Frames averaged"),
+                    )
+                    .on_hover_text("Fewer frames follow changes in frame rate more quickly, more give a steadier reading");
+                });
+                if let Some(average) = self.average_iterations.filter(|_| self.show_render_stats) {
+                    ui.label(format!("Average iterations per pixel: {average:.1}"));
+                }
                 #[cfg(not(target_arch = "wasm32"))]
                 ui.label("Fullscreen: [F11]");
 
                 ui.label("Toggle UI: [F1]");
+                ui.label("Pan: [Arrow keys] | Zoom: [+/-] | Auto zoom: [Space]");
+                ui.label("Zoom to area: [Shift+drag] | Grid: [G]");
+                ui.label("Reset view: [Double-click] | Centre on point: [Ctrl+double-click]");
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.undo_stack.is_empty() || self.settings != self.committed_settings, egui::Button::new("Undo")).on_hover_text("[Ctrl+Z]").clicked() {
+                        undo = true;
+                    }
+                    if ui.add_enabled(!self.redo_stack.is_empty(), egui::Button::new("Redo")).on_hover_text("[Ctrl+Shift+Z]").clicked() {
+                        redo = true;
+                    }
+                    if ui.button("Reset all").on_hover_text(RESET_SHORTCUT).clicked() {
+                        reset_all = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.refined_settings.is_none(), egui::Button::new("Refine"))
+                        .on_hover_text("Re-render the current view at full resolution with 4x4 anti-aliasing and 4 times the iterations, until it is next changed")
+                        .clicked()
+                    {
+                        self.refined_settings = Some(self.settings.clone());
+                        ui.ctx().request_repaint();
+                    }
+                    if self.refined_settings.is_some() {
+                        ui.label("Showing refined view");
+                    }
+                });
                 ui.separator();
 
-                ui.collapsing("Zoom [Scroll]", |ui| {
+                #[cfg(target_arch = "wasm32")]
+                if self.show_webgl_notice {
+                    ui.colored_label(Color32::YELLOW, "Your browser does not support WebGPU, so the WebGL fallback is being used. \
+                        Rendering may be slower, and high iteration counts may hit the browser's shader time limit and produce a black screen.");
+                    ui.horizontal(|ui| {
+                        ui.hyperlink_to("How to enable WebGPU", "https://github.com/gpuweb/gpuweb/wiki/Implementation-Status");
+                        if ui.button("Dismiss").clicked() {
+                            self.show_webgl_notice = false;
+                        }
+                    });
+                    ui.separator();
+                }
+
+                sections.show(ui, "Zoom [Scroll]", |ui| {
                     ui.label("Zoom");
+                    ui.horizontal(|ui| {
+                        let slider_max = ZOOM_SLIDER_MAX.max(self.settings.zoom);
+                        let drag_speed = self.settings.zoom * 0.01;
+                        ui.add(
+                            egui::Slider::new(&mut self.settings.zoom, MIN_ZOOM..=slider_max)
+                                .logarithmic(true)
+                                .show_value(false),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.zoom)
+                                .speed(drag_speed)
+                                .range(MIN_ZOOM..=MAX_ZOOM),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Exact zoom");
+                        let response = ui.add(egui::TextEdit::singleline(&mut self.zoom_text).desired_width(120.0))
+                            .on_hover_text("Scientific notation such as 1e12, or a power of ten such as 10^12");
+                        if response.changed() {
+                            match parse_zoom(&self.zoom_text) {
+                                Ok(zoom) => {
+                                    self.settings.zoom = zoom;
+                                    self.zoom_text_error = None;
+                                }
+                                Err(e) => self.zoom_text_error = Some(e),
+                            }
+                        }
+                        if !response.has_focus() {
+                            self.zoom_text = format!("{:e}", self.settings.zoom);
+                            self.zoom_text_error = None;
+                        }
+                        if let Some(e) = self.zoom_text_error {
+                            ui.colored_label(Color32::RED, e);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Scroll sensitivity");
+                        ui.add(egui::Slider::new(&mut self.settings.scroll_sensitivity, 0.1..=10.0).logarithmic(true));
+                    });
+                    ui.checkbox(&mut self.settings.invert_scroll, "Invert scroll direction");
+                    ui.checkbox(&mut self.settings.keep_scale_on_resize, "Keep scale when resizing")
+                        .on_hover_text("Show more or less of the fractal when the window is resized, instead of stretching the same region to fit");
+                    ui.horizontal(|ui| {
+                        ui.label("Zoom fits:");
+                        ui.radio_value(&mut self.settings.framing, Framing::ShorterSide, "Shorter side")
+                            .on_hover_text("The shorter side of the view always shows the same region, whatever the aspect ratio");
+                        ui.radio_value(&mut self.settings.framing, Framing::Width, "Width")
+                            .on_hover_text("The width always shows the same region and the height follows from the aspect ratio, so screenshots of any shape keep the view's horizontal framing");
+                    });
+                    ui.checkbox(&mut self.settings.high_precision, "High precision (slower, allows zooming further)");
+                    if self.settings.high_precision && !self.settings.shader_data.is_standard_equation() {
+                        ui.colored_label(Color32::YELLOW, "High precision only supports the standard Mandelbrot set/Julia set equation");
+                    } else if self.settings.high_precision && !self.settings.shader_data.has_default_escape_condition() {
+                        ui.colored_label(Color32::YELLOW, "High precision doesn't support custom escape conditions");
+                    }
+                    if ui.checkbox(&mut self.auto_zoom, "Auto zoom [Space]").changed() {
+                        self.auto_zoom_note = None;
+                    }
                     ui.add(
-                        egui::Slider::new(&mut self.settings.zoom, 0.0..=100000.0)
-                            .logarithmic(true),
+                        egui::Slider::new(&mut self.auto_zoom_rate, 1.01..=10.0)
+                            .logarithmic(true)
+                            .suffix("x per second"),
                     );
+                    if let Some(note) = &self.auto_zoom_note {
+                        ui.colored_label(Color32::YELLOW, note);
+                    }
                 });
                 ui.separator();
-                ui.collapsing("Iterations", |ui| {
+                sections.show(ui, "Iterations", |ui| {
                     ui.label("Iterations");
-                    ui.add(
-                        egui::Slider::new(&mut self.settings.iterations, 1..=10000)
-                            .logarithmic(true),
-                    );
+                    ui.horizontal(|ui| {
+                        let slider_max = ITERATIONS_SLIDER_MAX.max(self.settings.iterations);
+                        ui.add(
+                            egui::Slider::new(&mut self.settings.iterations, 1..=slider_max)
+                                .logarithmic(true)
+                                .show_value(false),
+                        );
+                        ui.add(egui::DragValue::new(&mut self.settings.iterations).range(1..=MAX_ITERATIONS));
+                    });
+                    ui.checkbox(&mut self.settings.auto_iterations, "Increase with zoom")
+                        .on_hover_text("Adds iterations each time the zoom doubles, using the value above as the base");
+                    if self.settings.auto_iterations {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.settings.auto_iterations_rate).range(0.0..=10000.0));
+                            ui.label("iterations per doubling of zoom");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.settings.auto_iterations_limit).range(1..=MAX_ITERATIONS));
+                            ui.label("maximum iterations");
+                        });
+                        ui.label(format!("Currently rendering with {} iterations", effective_iterations(&self.settings)));
+                    }
                     ui.label("Escape threshold");
                     ui.add(
                         egui::Slider::new(
@@ -306,9 +2101,37 @@ impl eframe::App for FractalViewerApp {
                         )
                             .logarithmic(true),
                     );
+                    ui.label("Escape condition:")
+                        .on_hover_text("A boolean expression for when to stop iterating. Available variables: z, c, trap and threshold (the escape threshold above). High precision mode and the interior check are only used with the default, length(z) >= threshold.");
+                    ui.horizontal(|ui| {
+                        if ui.text_edit_singleline(&mut self.settings.shader_data.escape_condition).changed() {
+                            self.recompile_shader = true;
+                        }
+                        if ui.button("Reset").clicked() {
+                            self.settings.shader_data.escape_condition = DEFAULT_ESCAPE_CONDITION.to_string();
+                            self.recompile_shader = true;
+                        }
+                    });
+                    ui.checkbox(&mut self.show_iteration_probe, "Show iterations under cursor");
+                    ui.checkbox(&mut self.show_render_stats, "Show average iterations per pixel")
+                        .on_hover_text("Renders the view again at low resolution every frame to count iterations, which reduces FPS");
+
+                    if self.show_iteration_probe {
+                        match self.probe_result {
+                            Some(ProbeResult { iterations, escaped: true }) => {
+                                ui.label(format!("Escaped after {iterations} iterations"));
+                            }
+                            Some(ProbeResult { escaped: false, .. }) => {
+                                ui.label(format!("Did not escape within {} iterations", effective_iterations(&self.settings)));
+                            }
+                            None => {
+                                ui.label("Hover over the fractal to see its iteration count");
+                            }
+                        }
+                    }
                 });
                 ui.separator();
-                ui.collapsing("Centre [Click and drag to pan]", |ui| {
+                sections.show(ui, "Centre [Click and drag to pan]", |ui| {
                     ui.label("Centre");
                     ui.add(
                         egui::DragValue::new(&mut self.settings.centre[0])
@@ -322,91 +2145,518 @@ impl eframe::App for FractalViewerApp {
                     if ui.button("Reset").clicked() {
                         self.settings.centre = [0.0, 0.0];
                     }
+                    ui.checkbox(&mut self.settings.pan_inertia, "Keep moving after releasing a drag");
+                    ui.checkbox(&mut self.settings.show_grid, "Show axes and grid [G]");
                 });
                 ui.separator();
-                ui.checkbox(&mut self.settings.julia_set, "Julia set");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.settings.mode, FractalMode::EscapeTime, "Escape time");
+                    ui.radio_value(&mut self.settings.mode, FractalMode::Newton, "Newton's method");
+                });
+                let newton = self.settings.mode == FractalMode::Newton;
+                ui.add_enabled(!newton, egui::Checkbox::new(&mut self.settings.julia_set, "Julia set"));
+                ui.add_enabled(
+                    !newton && !self.settings.julia_set,
+                    egui::Checkbox::new(&mut self.show_julia_preview, "Preview Julia set for the point under the cursor"),
+                );
+                ui.add_enabled(
+                    self.settings.julia_set && !newton,
+                    egui::Checkbox::new(&mut self.julia_follows_centre, "Value of c follows centre when panning"),
+                );
+                let can_morph = !newton && !self.settings.julia_set && self.settings.shader_data.supports_julia_morph();
+                ui.add_enabled_ui(can_morph, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.add(egui::Slider::new(&mut self.settings.julia_morph, 0.0..=1.0).text("Morph to Julia set"))
+                            .on_hover_text("Morphs towards the Julia set for the value of c set in Julia mode, or by right clicking while morphing. Only available for z^p + c equations.")
+                            .changed()
+                        {
+                            self.julia_morph_direction = None;
+                        }
+                        if ui.button("Animate").clicked() {
+                            self.julia_morph_direction = Some(if self.settings.julia_morph < 0.5 { 1.0 } else { -1.0 });
+                            ui.ctx().request_repaint();
+                        }
+                    });
+                });
+                ui.add_enabled(
+                    supports_orbit(&self.settings),
+                    egui::Checkbox::new(&mut self.show_orbit, "Show orbit of clicked point"),
+                )
+                .on_hover_text(format!("Draws up to {} values of z. Only available for the Mandelbrot set, burning ship, tricorn and z^3 + c equations.", orbit::MAX_ORBIT_POINTS));
                 ui.separator();
-                ui.collapsing("Initial value [Hold right click and drag]", |ui| {
-                    ui.label("Initial value of z");
-                    ui.label("(or value of c for Julia sets)");
-                    ui.add(egui::DragValue::new(&mut self.settings.initial_value[0]).speed(0.01));
-                    ui.add(
-                        egui::DragValue::new(&mut self.settings.initial_value[1])
-                            .speed(0.01)
-                            .suffix("i"),
-                    );
+                sections.show(ui, "Initial value [Hold right click and drag]", |ui| {
+                    let julia = self.settings.julia_set && !newton;
+                    let value = if julia {
+                        ui.label("Value of c for the Julia set");
+                        &mut self.settings.julia_c
+                    } else {
+                        ui.label("Initial value of z");
+                        &mut self.settings.initial_value
+                    };
+                    ui.add(egui::DragValue::new(&mut value[0]).speed(0.01));
+                    ui.add(egui::DragValue::new(&mut value[1]).speed(0.01).suffix("i"));
                     if ui.button("Reset").clicked() {
-                        self.settings.initial_value = [0.0, 0.0];
+                        *value = [0.0, 0.0];
                     }
                     ui.checkbox(&mut self.settings.initial_c, "Add c to initial value");
                 });
                 ui.separator();
-                ui.collapsing("Equation", |ui| {
+                let equation_response = sections.header("Equation").show(ui, |ui| {
                     ui.label("Iterative function (WGSL expression)");
                     egui::ComboBox::from_label("Iterative function")
                         .selected_text("Select default equation")
                         .show_ui(ui, |ui| {
-                            if ui.selectable_value(
-                                &mut self.settings.shader_data.equation,
-                                "csquare(z) + c".to_string(),
-                                "Mandelbrot set",
-                            ).clicked() || ui.selectable_value(
-                                &mut self.settings.shader_data.equation,
-                                "csquare(abs(z)) + c".to_string(),
-                                "Burning ship fractal",
-                            ).clicked() || ui.selectable_value(
-                                &mut self.settings.shader_data.equation,
-                                "cdiv(cmul(csquare(z), z), vec2<f32>(1.0, 0.0) + z * z) + c"
-                                    .to_string(),
-                                "Feather fractal",
-                            ).clicked() || ui.selectable_value(
-                                &mut self.settings.shader_data.equation,
-                                "csquare(vec2<f32>(z.x, -z.y)) + c".to_string(),
-                                "Tricorn fractal",
-                            ).clicked() {
-                                self.recompile_shader = true;
+                            for preset in EQUATION_PRESETS {
+                                if ui.selectable_value(
+                                    &mut self.settings.shader_data.equation,
+                                    preset.equation.to_string(),
+                                    preset.name,
+                                ).clicked() {
+                                    self.settings.shader_data.colour = preset.colour.to_string();
+                                    self.recompile_shader = true;
+                                }
                             }
                         });
+                    #[cfg(not(target_arch = "wasm32"))]
+                    sections.show(ui, "Gallery", |ui| {
+                        let gallery = self.preset_gallery.get_or_insert_with(|| PresetGallery::new(ui.ctx()));
+                        if let Some(preset) = gallery.show(ui) {
+                            self.settings.shader_data.equation = preset.equation.to_string();
+                            self.settings.shader_data.colour = preset.colour.to_string();
+                            self.recompile_shader = true;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Multibrot z^n + c:");
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut self.settings.multibrot_power)
+                                    .speed(0.05)
+                                    .fixed_decimals(2)
+                                    .range(MULTIBROT_POWERS)
+                                    .prefix("n = "),
+                            )
+                            .on_hover_text("Sets the equation. Whole powers are exact; others have a seam along the negative real axis.")
+                            .changed()
+                        {
+                            self.settings.mode = FractalMode::EscapeTime;
+                            self.settings.shader_data.equation = multibrot_equation(self.settings.multibrot_power);
+                            self.recompile_shader = true;
+                        }
+                    });
+                    if ui.button("Newton fractal for z^3 - 1").clicked() {
+                        self.settings.mode = FractalMode::Newton;
+                        self.settings.shader_data.equation = NEWTON_EQUATION.to_string();
+                        self.settings.shader_data.derivative = NEWTON_DERIVATIVE.to_string();
+                        self.settings.roots = UserSettings::default().roots;
+                        self.recompile_shader = true;
+                    }
                     ui.label("...Or edit it yourself!");
-                    if ui.add(TextEdit::singleline(&mut self.settings.shader_data.equation).desired_width(ui.max_rect().width())).changed() {
+                    if ui.add(TextEdit::multiline(&mut self.settings.shader_data.equation).code_editor().desired_rows(2).desired_width(ui.max_rect().width()).layouter(&mut highlight::layout_wgsl)).changed() {
                         self.recompile_shader = true;
                     };
-                    ui.label("Colour expression:");
+                    if self.settings.mode == FractalMode::Newton {
+                        ui.label("Derivative of the function (WGSL expression)");
+                        if ui.add(TextEdit::multiline(&mut self.settings.shader_data.derivative).code_editor().desired_rows(2).desired_width(ui.max_rect().width()).layouter(&mut highlight::layout_wgsl)).changed() {
+                            self.recompile_shader = true;
+                        };
+                        ui.label("Roots to colour by");
+                        let mut to_remove = None;
+                        for (index, root) in self.settings.roots.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut root[0]).speed(0.01));
+                                ui.add(egui::DragValue::new(&mut root[1]).speed(0.01).suffix("i"));
+                                if ui.button("Remove").clicked() {
+                                    to_remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = to_remove {
+                            self.settings.roots.remove(index);
+                        }
+                        if ui
+                            .add_enabled(self.settings.roots.len() < MAX_ROOTS, egui::Button::new("Add root"))
+                            .clicked()
+                        {
+                            self.settings.roots.push([0.0, 0.0]);
+                        }
+                    }
+                    egui::ComboBox::from_label("Palette")
+                        .selected_text("Select a palette")
+                        .show_ui(ui, |ui| {
+                            for (name, colour) in COLOUR_EXPRESSION_PRESETS {
+                                if ui.selectable_value(
+                                    &mut self.settings.shader_data.colour,
+                                    colour.to_string(),
+                                    *name,
+                                ).clicked() {
+                                    self.recompile_shader = true;
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("hsv_rgb, hsl_rgb and oklab_rgb can all be used in custom colour expressions");
+                    ui.label("Colour expression:")
+                        .on_hover_text("Available variables: n (iteration count, smoothed if enabled), i (integer iteration count), sn (smoothed iteration count), z (final value of z) and c");
                     ui.horizontal(|ui| {
                         if ui.text_edit_singleline(&mut self.settings.shader_data.colour).changed() {
                             self.recompile_shader = true;
                         };
                         if ui.button("Reset").clicked() {
-                            self.settings.shader_data.colour = "hsv_rgb(vec3(log(n + 1.0) / log(f32(uniforms.iterations) + 1.0), 0.8, 0.8))".to_string();
+                            self.settings.shader_data.colour = DEFAULT_COLOUR.to_string();
+                            self.recompile_shader = true;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let has_palette = !self.settings.palette.is_empty();
+                        ui.add_enabled(has_palette, egui::Checkbox::new(&mut self.settings.use_palette, "Use palette image"))
+                            .on_hover_text("Colour by looking up the log-scaled iteration count in a gradient image instead of using the colour expression");
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui.button("Load...").on_hover_text("The middle row of the image is used, from the lowest iteration count on the left to the highest on the right").clicked() {
+                            load_palette = true;
+                        }
+                        if ui.add_enabled(has_palette, egui::Button::new("Clear")).clicked() {
+                            self.settings.palette.clear();
+                            self.settings.use_palette = false;
+                        }
+                    });
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(e) = &self.palette_error {
+                        ui.colored_label(Color32::RED, format!("Couldn't load palette: {e}"));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Invert colours:");
+                        ui.radio_value(&mut self.settings.invert_colours, InvertColours::Off, "Off");
+                        ui.radio_value(&mut self.settings.invert_colours, InvertColours::Iterations, "Reverse gradient")
+                            .on_hover_text("Counts n and sn down from the iteration limit, so the palette runs the other way");
+                        ui.radio_value(&mut self.settings.invert_colours, InvertColours::Rgb, "Negative")
+                            .on_hover_text("Subtracts the colour from white");
+                    });
+                    ui.checkbox(&mut self.greyscale_iterations, "Greyscale iterations")
+                        .on_hover_text("Show the iteration count in greyscale instead of using the colour expression, to help with writing equations");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.cycle_colours, "Cycle colours")
+                            .on_hover_text("Advances uniforms.time, which colour expressions can use to animate the palette, e.g. fract(n / 50.0 + uniforms.time * 0.1)");
+                        if ui.add_enabled(self.colour_time != 0.0, egui::Button::new("Reset time")).clicked() {
+                            self.colour_time = 0.0;
+                        }
+                    });
+
+                    let supports_de = self.settings.shader_data.supports_distance_estimation();
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            supports_de,
+                            egui::Checkbox::new(&mut self.settings.distance_estimation, "Distance estimation"),
+                        )
+                        .on_hover_text("Makes the estimated distance to the set in pixels available to the colour expression as `de`");
+                        if ui.add_enabled(supports_de, egui::Button::new("Use distance colour")).clicked() {
+                            self.settings.distance_estimation = true;
+                            self.settings.shader_data.colour = DISTANCE_ESTIMATION_COLOUR.to_string();
+                            self.recompile_shader = true;
+                        }
+                    });
+                    if !supports_de {
+                        ui.label("Distance estimation is not available for equations using abs");
+                    }
+
+                    sections.show(ui, "Orbit trap", |ui| {
+                        let trap = &mut self.settings.orbit_trap;
+                        egui::ComboBox::from_label("Shape")
+                            .selected_text(match trap.shape {
+                                OrbitTrapShape::None => "None",
+                                OrbitTrapShape::Point => "Point",
+                                OrbitTrapShape::Line => "Line",
+                                OrbitTrapShape::Cross => "Cross",
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut trap.shape, OrbitTrapShape::None, "None");
+                                ui.selectable_value(&mut trap.shape, OrbitTrapShape::Point, "Point");
+                                ui.selectable_value(&mut trap.shape, OrbitTrapShape::Line, "Line");
+                                ui.selectable_value(&mut trap.shape, OrbitTrapShape::Cross, "Cross");
+                            });
+                        ui.horizontal(|ui| {
+                            ui.label("Centre");
+                            ui.add(egui::DragValue::new(&mut trap.centre[0]).speed(0.01));
+                            ui.add(egui::DragValue::new(&mut trap.centre[1]).speed(0.01).suffix("i"));
+                        });
+                        ui.add_enabled(
+                            matches!(trap.shape, OrbitTrapShape::Line | OrbitTrapShape::Cross),
+                            egui::Slider::new(&mut trap.angle, 0.0..=std::f32::consts::PI).text("Angle"),
+                        );
+                        if ui.button("Use orbit trap colour").clicked() {
+                            if trap.shape == OrbitTrapShape::None {
+                                trap.shape = OrbitTrapShape::Point;
+                            }
+                            self.settings.shader_data.colour = ORBIT_TRAP_COLOUR.to_string();
                             self.recompile_shader = true;
                         }
                     });
 
-                    ui.label("Additional code to include in shader:");
-                    if ui.add(TextEdit::multiline(&mut self.settings.shader_data.additional).code_editor()).changed() {
+                    ui.label("Additional code to include in shader:")
+                        .on_hover_text("Helper functions defined here can be called from the equation and colour expressions");
+                    if ui
+                        .add(
+                            TextEdit::multiline(&mut self.settings.shader_data.additional)
+                                .code_editor()
+                                .desired_width(ui.max_rect().width())
+                                .layouter(&mut highlight::layout_wgsl)
+                                .hint_text("fn my_function(z: vec2<f32>) -> vec2<f32> { ... }"),
+                        )
+                        .changed()
+                    {
                         self.recompile_shader = true;
                     };
 
-                    ui.checkbox(&mut self.settings.internal_black, "Always colour inside of set black");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.settings.internal_black, "Fill inside of set with");
+                        ui.add_enabled_ui(self.settings.internal_black, |ui| {
+                            ui.color_edit_button_srgb(&mut self.settings.interior_colour);
+                        });
+                    });
+                    ui.add_enabled_ui(self.settings.internal_black, |ui| {
+                        ui.label("Interior colour expression:")
+                            .on_hover_text("Available variables: z (value of z when iteration stopped), c, trap and period (length of the cycle the orbit settles into, or 0 if not found; only set with period colouring below). The default, period_colour(period), is the colour picked above, or a hue per period.");
+                        ui.horizontal(|ui| {
+                            if ui.text_edit_singleline(&mut self.settings.shader_data.interior_colour).changed() {
+                                self.recompile_shader = true;
+                            }
+                            if ui.button("Reset").clicked() {
+                                self.settings.shader_data.interior_colour = DEFAULT_INTERIOR_COLOUR.to_string();
+                                self.recompile_shader = true;
+                            }
+                        });
+                    });
+                    ui.add_enabled(
+                        self.settings.internal_black && self.settings.shader_data.is_standard_equation(),
+                        egui::Checkbox::new(&mut self.settings.interior_check, "Skip iterating inside of set (faster)"),
+                    )
+                    .on_hover_text("Detects points in the main cardioid and bulb, and orbits that repeat. Only used for the standard Mandelbrot set with an initial value of 0.");
+                    ui.add_enabled(
+                        self.settings.internal_black
+                            && !self.settings.julia_set
+                            && self.settings.mode == FractalMode::EscapeTime
+                            && self.settings.shader_data.is_standard_equation(),
+                        egui::Checkbox::new(&mut self.settings.period_colouring, "Colour inside of set by period (slower)"),
+                    )
+                    .on_hover_text("Colours each bulb of the Mandelbrot set by the length of the cycle its orbits settle into. Only used for the standard Mandelbrot set, and not in high precision mode.");
 
                     if let Some(e) = &self.shader_error {
-                        ui.colored_label(Color32::RED, format!("Invalid expression: {e}"));
+                        ui.label(
+                            egui::RichText::new(format!("{}: {e}", e.kind()))
+                                .color(Color32::RED)
+                                .monospace(),
+                        );
                     }
                 });
+                sections.record("Equation", &equation_response);
 
                 {
                     ui.separator();
-                    ui.checkbox(&mut self.settings.smoothen, "Smoothen (warning: only produces correct results on a normal Mandelbrot set!)");
+                    sections.show(ui, "Compare", |ui| {
+                        ui.label("Show a copy of the settings to the right of a divider, then change these ones to see the difference");
+                        ui.horizontal(|ui| {
+                            start_comparison = ui.button("Compare with current settings").clicked();
+                            swap_comparison = ui
+                                .add_enabled(self.comparison.is_some(), egui::Button::new("Swap sides"))
+                                .on_hover_text("Edit the settings on the right instead")
+                                .clicked();
+                            if ui
+                                .add_enabled(self.comparison.is_some(), egui::Button::new("Stop"))
+                                .clicked()
+                            {
+                                self.comparison = None;
+                            }
+                        });
+                    });
                 }
                 {
                     ui.separator();
-                    egui::CollapsingHeader::new("Export and import options")
-                        .default_open(self.import_error.is_some())
+                    sections.show(ui, "Bookmarks", |ui| {
+                        let mut changed = false;
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.bookmark_name);
+                            if ui
+                                .add_enabled(!self.bookmark_name.is_empty(), egui::Button::new("Save current view"))
+                                .clicked()
+                            {
+                                let name = std::mem::take(&mut self.bookmark_name);
+                                self.bookmarks.push((name, self.settings.clone()));
+                                changed = true;
+                            }
+                        });
+
+                        let mut to_remove = None;
+                        for (index, (name, settings)) in self.bookmarks.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(name.as_str());
+                                if ui.button("Load").clicked() {
+                                    self.settings = settings.clone();
+                                    self.recompile_shader = true;
+                                }
+                                if ui.button("Delete").clicked() {
+                                    to_remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = to_remove {
+                            self.bookmarks.remove(index);
+                            changed = true;
+                        }
+
+                        if changed {
+                            self.bookmark_error = bookmarks::save(&self.bookmarks).err();
+                        }
+                        if let Some(e) = &self.bookmark_error {
+                            ui.colored_label(Color32::RED, format!("Saving bookmarks failed: {e}"));
+                        }
+                    });
+                }
+                {
+                    ui.separator();
+                    sections.show(ui, "Saved colours", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.colour_preset_name);
+                            if ui
+                                .add_enabled(!self.colour_preset_name.is_empty(), egui::Button::new("Save current colour"))
+                                .clicked()
+                            {
+                                let name = std::mem::take(&mut self.colour_preset_name);
+                                self.colour_presets.push(ColourPreset::from_settings(name, &self.settings));
+                            }
+                        });
+
+                        let mut to_remove = None;
+                        for (index, preset) in self.colour_presets.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(preset.name.as_str());
+                                if ui.button("Apply").clicked() {
+                                    preset.apply(&mut self.settings);
+                                    self.recompile_shader = true;
+                                }
+                                if ui.button("Export to clipboard").clicked() {
+                                    ui.output_mut(|o| o.copied_text = preset.export_string());
+                                }
+                                if ui.button("Delete").clicked() {
+                                    to_remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = to_remove {
+                            self.colour_presets.remove(index);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.colour_preset_import);
+                            if ui.button("Import").clicked() {
+                                match ColourPreset::import_string(&self.colour_preset_import) {
+                                    Ok(preset) => {
+                                        self.colour_presets.push(preset);
+                                        self.colour_preset_import.clear();
+                                        self.colour_preset_error = None;
+                                    }
+                                    Err(e) => self.colour_preset_error = Some(e.to_string()),
+                                }
+                            }
+                        });
+                        if let Some(e) = &self.colour_preset_error {
+                            ui.colored_label(Color32::RED, format!("Import failed: {e}"));
+                        }
+                    });
+                }
+                {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        // Turning smoothing on or off goes all the way, and the slider can blend
+                        // between the two
+                        if ui.checkbox(&mut self.settings.smoothen, "Smoothen").changed() {
+                            self.settings.smoothing_strength = if self.settings.smoothen { 1.0 } else { 0.0 };
+                        }
+                        if ui.add(egui::Slider::new(&mut self.settings.smoothing_strength, 0.0..=1.0).text("Strength")).changed() {
+                            self.settings.smoothen = self.settings.smoothing_strength > 0.0;
+                        }
+                    });
+                    if self.settings.smoothen {
+                        ui.horizontal(|ui| match self.settings.shader_data.equation_power() {
+                            Some(power) => {
+                                ui.label(format!("Equation power: {power}"));
+                            }
+                            None => {
+                                ui.label("Equation power");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.settings.smoothing_power)
+                                        .speed(0.01)
+                                        .range(1.01..=f32::MAX),
+                                )
+                                .on_hover_text("The power of z in the equation, used to work out how smooth colouring should look");
+                            }
+                        });
+                    }
+                }
+                {
+                    ui.separator();
+                    ui.label("Render scale");
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.render_scale, MIN_RENDER_SCALE..=1.0)
+                            .custom_formatter(|n, _| format!("{:.0}%", n * 100.0)),
+                    )
+                    .on_hover_text("Render at a lower resolution and upscale, for higher FPS");
+                    egui::ComboBox::from_label("Frame rate limit")
+                        .selected_text(self.settings.frame_rate_limit.to_string())
+                        .show_ui(ui, |ui| {
+                            for limit in [FrameRateLimit::Uncapped, FrameRateLimit::Fps60, FrameRateLimit::Fps30, FrameRateLimit::OnDemand] {
+                                ui.selectable_value(&mut self.settings.frame_rate_limit, limit, limit.to_string());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Limits how often the view is redrawn while it changes continuously, to save power");
+                    ui.label("Anti-aliasing");
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.samples, 1..=4)
+                            .custom_formatter(|n, _| if n == 1.0 { "Off".to_string() } else { format!("{n}x{n}") }),
+                    );
+                    if self.settings.samples > 1 {
+                        ui.colored_label(
+                            Color32::YELLOW,
+                            format!("Renders {} samples per pixel, reducing FPS", self.settings.samples * self.settings.samples),
+                        );
+                    }
+                    ui.add_enabled(
+                        self.settings.samples == 1
+                            && self.settings.internal_black
+                            && self.settings.mode == FractalMode::EscapeTime,
+                        egui::Checkbox::new(&mut self.settings.edge_antialiasing, "Smooth edge of set"),
+                    )
+                    .on_hover_text("Blends the filled inside of the set with the outside where they meet, by testing which parts of each pixel escape. Iterates as much as 2x2 anti-aliasing, but works out at most two colours per pixel, which helps with expensive colour expressions. Only smooths that edge. Not used in high precision mode.");
+                    egui::ComboBox::from_label("MSAA")
+                        .selected_text(if self.msaa_samples > 1 { format!("{}x", self.msaa_samples) } else { "Off".to_string() })
+                        .show_ui(ui, |ui| {
+                            for count in MSAA_SAMPLE_COUNTS {
+                                let text = if count > 1 { format!("{count}x") } else { "Off".to_string() };
+                                ui.add_enabled_ui(self.supported_sample_counts.contains(&count), |ui| {
+                                    ui.selectable_value(&mut self.msaa_samples, count, text);
+                                });
+                            }
+                        })
+                        .response
+                        .on_hover_text("Hardware multisampling of the on-screen view. The fractal is drawn as one full-screen shape, so MSAA only affects its edges; the anti-aliasing above smooths the fractal itself.");
+                    // Checked on the fields, as the window already borrows part of self
+                    if !self.supported_sample_counts.contains(&self.msaa_samples) {
+                        ui.colored_label(
+                            Color32::YELLOW,
+                            format!("{}x MSAA isn't supported by this GPU, so it is off", self.msaa_samples),
+                        );
+                    }
+                }
+                {
+                    ui.separator();
+                    let export_response = egui::CollapsingHeader::new("Export and import options")
+                        .default_open(sections.is_open("Export and import options") || self.import_error.is_some())
                         .show(ui, |ui| {
-                            if ui.button("Export to clipboard").clicked() {
+                            let description = self.settings.describe();
+                            if ui.button("Export to clipboard").on_hover_text(&description).clicked() {
                                 ui.output_mut(|o| o.copied_text = self.settings.export_string());
                             }
-                            if ui.button("Export link to clipboard").clicked() {
+                            if ui.button("Export link to clipboard").on_hover_text(&description).clicked() {
                                 ui.output_mut(|o| o.copied_text = format!("{}?{}", option_env!("SITE_LINK").unwrap_or("https://arthomnix.dev/fractal/"), self.settings.export_string()));
                             }
                             // Reading clipboard doesn't work in Firefox, so we only support importing from link on web
@@ -422,12 +2672,137 @@ impl eframe::App for FractalViewerApp {
                                     Err(e) => self.import_error = Some(e.to_string()),
                                 };
                             }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            ui.horizontal(|ui| {
+                                if ui.button("Export to file...").on_hover_text(&description).clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("Fractal settings", &["txt"])
+                                        .set_file_name("fractal.txt")
+                                        .save_file()
+                                    {
+                                        self.export_error = std::fs::write(path, self.settings.export_string()).err().map(|e| e.to_string());
+                                    }
+                                }
+                                if ui.button("Import from file...").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("Fractal settings", &["txt"])
+                                        .pick_file()
+                                    {
+                                        match std::fs::read_to_string(path) {
+                                            Ok(text) => match UserSettings::import_string(text.trim()) {
+                                                Ok(settings) => {
+                                                    self.settings = settings;
+                                                    self.import_error = None;
+                                                    self.recompile_shader = true;
+                                                }
+                                                Err(e) => self.import_error = Some(e.to_string()),
+                                            },
+                                            Err(e) => self.import_error = Some(e.to_string()),
+                                        }
+                                    }
+                                }
+                                if ui.button("Open image...").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("PNG image", &["png"])
+                                        .pick_file()
+                                    {
+                                        match std::fs::read(path) {
+                                            Ok(data) => match UserSettings::import_png(&data) {
+                                                Ok(settings) => {
+                                                    self.settings = settings;
+                                                    self.import_error = None;
+                                                    self.recompile_shader = true;
+                                                }
+                                                Err(e) => self.import_error = Some(e.to_string()),
+                                            },
+                                            Err(e) => self.import_error = Some(e.to_string()),
+                                        }
+                                    }
+                                }
+                            });
                             if let Some(e) = &self.import_error {
                                 ui.colored_label(Color32::RED, format!("Import failed: {e}"));
                             }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if let Some(e) = &self.export_error {
+                                ui.colored_label(Color32::RED, format!("Export failed: {e}"));
+                            }
+
+                            #[cfg(not(target_arch = "wasm32"))]
+                            {
+                                ui.separator();
+                                let max_size = MAX_SCREENSHOT_SIZE;
+                                ui.horizontal(|ui| {
+                                    ui.label("Screenshot size:");
+                                    ui.add(egui::DragValue::new(&mut self.screenshot_size[0]).range(1..=max_size));
+                                    ui.label("x");
+                                    ui.add_enabled(
+                                        !self.screenshot_match_aspect,
+                                        egui::DragValue::new(&mut self.screenshot_size[1]).range(1..=max_size),
+                                    );
+                                });
+                                ui.checkbox(&mut self.screenshot_match_aspect, "Match aspect ratio of view");
+                                if self.screenshot_match_aspect {
+                                    let aspect = self.view_size.y / self.view_size.x;
+                                    self.screenshot_size[1] = ((self.screenshot_size[0] as f32 * aspect).round() as u32).clamp(1, max_size);
+                                }
+                                if ui.button("Save screenshot...").clicked() {
+                                    save_screenshot = true;
+                                }
+                                if let Some(e) = &self.screenshot_error {
+                                    ui.colored_label(Color32::RED, format!("Saving screenshot failed: {e}"));
+                                }
+
+                                ui.separator();
+                                ui.label("Zoom animation (rendered at the screenshot size)");
+                                let animation = &mut self.animation;
+                                for (label, view) in [("Start", &mut animation.start), ("End", &mut animation.end)] {
+                                    ui.horizontal(|ui| {
+                                        if ui.button(format!("Set {} to current view", label.to_lowercase())).clicked() {
+                                            *view = Some(self.settings.clone());
+                                        }
+                                        match view {
+                                            Some(view) => ui.label(format!("{label} zoom: {:.3e}", view.zoom)),
+                                            None => ui.label(format!("{label} not set")),
+                                        };
+                                    });
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.label("Frames:");
+                                    ui.add(egui::DragValue::new(&mut animation.frames).range(2..=100000));
+                                });
+                                ui.checkbox(&mut animation.ramp_iterations, "Increase iterations from the start view's to the end view's");
+                                let mut cancel = false;
+                                match &animation.recording {
+                                    Some(recording) => {
+                                        ui.horizontal(|ui| {
+                                            ui.label(format!("Rendering frame {}/{}", recording.next_frame + 1, animation.frames));
+                                            cancel = ui.button("Cancel").clicked();
+                                        });
+                                    }
+                                    None => {
+                                        if ui
+                                            .add_enabled(
+                                                animation.start.is_some() && animation.end.is_some(),
+                                                egui::Button::new("Render frames..."),
+                                            )
+                                            .clicked()
+                                        {
+                                            start_recording = true;
+                                        }
+                                    }
+                                }
+                                if cancel {
+                                    animation.recording = None;
+                                }
+                                if let Some(e) = &animation.error {
+                                    ui.colored_label(Color32::RED, format!("Rendering animation failed: {e}"));
+                                }
+                            }
                             #[cfg(target_arch = "wasm32")]
                             ui.label("To import a settings string on web, add '?<string>' to the end of this page's URL.")
                         });
+                    sections.record("Export and import options", &export_response);
                 }
 
                 #[cfg(target_arch = "wasm32")]
@@ -440,10 +2815,92 @@ impl eframe::App for FractalViewerApp {
                     })
                 }
             });
+        self.open_sections = sections;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if save_screenshot {
+            self.save_screenshot();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if start_recording {
+            self.start_recording();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if load_palette {
+            self.load_palette();
+        }
+        if start_comparison {
+            self.start_comparison();
+        }
+        if swap_comparison {
+            self.swap_comparison();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.record_animation_frame(ctx);
+        if undo {
+            self.undo();
+        } else if redo {
+            self.redo();
+        }
+        if reset_all {
+            self.request_reset_all();
+        }
+
+        if self.confirm_reset {
+            let mut confirmed = false;
+            let mut cancelled = false;
+            egui::Window::new("Reset all settings?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("This will also discard your custom equation and colour expressions.");
+                    ui.horizontal(|ui| {
+                        confirmed = ui.button("Reset").clicked();
+                        cancelled = ui.button("Cancel").clicked();
+                    });
+                });
+            if confirmed {
+                self.reset_all();
+            } else if cancelled || ctx.input(|i| i.key_pressed(Key::Escape)) {
+                self.confirm_reset = false;
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(watcher) = &mut self.shader_watcher {
+            match watcher.poll() {
+                Ok(Some(source)) => {
+                    log::info!("Reloaded shader");
+                    // The validation cache only knows about the custom expressions
+                    self.shader_validator = ShaderValidator::with_template(source.into());
+                    self.recompile_shader = true;
+                    // The compared side is recompiled with the new shader too, so needs the
+                    // same check
+                    if let Some(comparison) = &mut self.comparison {
+                        comparison.error = self
+                            .shader_validator
+                            .validate_shader(&comparison.settings.shader_data)
+                            .err();
+                    }
+                }
+                Ok(None) => {}
+                Err(message) => {
+                    self.shader_error = Some(ShaderError::Reload(ShaderErrorDetails {
+                        message,
+                        location: None,
+                    }));
+                }
+            }
+            ctx.request_repaint_after(hot_reload::POLL_INTERVAL);
+        }
 
         // Validate custom expressions
         if self.recompile_shader {
-            if let Err(e) = validate_shader(&self.settings.shader_data) {
+            if let Err(e) = self
+                .shader_validator
+                .validate_shader(&self.settings.shader_data)
+            {
                 self.shader_error = Some(e);
                 self.recompile_shader = false;
             } else {
@@ -451,92 +2908,690 @@ impl eframe::App for FractalViewerApp {
             }
         }
 
-        self.prev_frame_time = self.last_frame.elapsed();
-        let new_fps = self.prev_frame_time.as_secs_f32().recip();
-        self.fps_samples.push_back(new_fps);
-        if self.fps_samples.len() > 200 {
-            self.fps_samples.pop_front();
-        }
-        self.last_frame = Instant::now();
+        self.update_undo_history(ctx);
     }
 }
 
 struct RendererState {
     device: Arc<Device>,
     target_format: ColorTargetState,
-    bind_group_layout: BindGroupLayout,
+    /// Shared so that pipelines can be created on another thread
+    bind_group_layout: Arc<BindGroupLayout>,
     bind_group: BindGroup,
     uniform_buffer: Buffer,
+    palette: PaletteTexture,
 }
 
 impl RendererState {
-    fn generate_pipeline(&self, shader_data: &CustomShaderData) -> RenderPipeline {
-        let shader = self.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some("fv_shader"),
-            source: ShaderSource::Wgsl(shader_data.shader().into()),
+    fn new(
+        device: Arc<Device>,
+        queue: &Queue,
+        target_format: ColorTargetState,
+        uniforms: Uniforms,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fv_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
-        let pipeline_layout = self
-            .device
-            .create_pipeline_layout(&PipelineLayoutDescriptor {
-                label: Some("fv_pipeline_layout"),
-                bind_group_layouts: &[&self.bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        self.device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("fv_pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &[],
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fv_uniform_bind_group_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
                 },
-                fragment: Some(FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(self.target_format.clone())],
-                }),
-                primitive: PrimitiveState::default(),
-                depth_stencil: None,
-                multisample: MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            })
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_uniform_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let palette = PaletteTexture::new(&device, queue);
+
+        Self {
+            device,
+            target_format,
+            bind_group_layout: Arc::new(bind_group_layout),
+            bind_group,
+            uniform_buffer,
+            palette,
+        }
+    }
+
+    fn set_palette(&mut self, queue: &Queue, pixels: &[u8]) {
+        self.palette.set(&self.device, queue, pixels);
+    }
+
+    /// Bind the palette, which every render pass using the fractal pipeline needs alongside its
+    /// uniforms
+    fn bind_palette(&self, render_pass: &mut RenderPass<'_>) {
+        render_pass.set_bind_group(1, self.palette.bind_group(), &[]);
+    }
+
+    fn is_bgra(&self) -> bool {
+        matches!(
+            self.target_format.format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Flags describing the render target, added to all uniforms written for this state
+    fn target_flags(&self) -> u32 {
+        if self.target_format.format.is_srgb() {
+            SRGB_TARGET
+        } else {
+            0
+        }
+    }
+
+    fn create_pipeline(&self, shader: &ShaderModule, sample_count: u32) -> RenderPipeline {
+        create_pipeline(
+            &self.device,
+            &[&self.bind_group_layout, self.palette.bind_group_layout()],
+            &self.target_format,
+            shader,
+            sample_count,
+        )
+    }
+
+    fn compile(&self, source: String, sample_count: u32) -> CompiledShader {
+        let shader = create_shader_module(&self.device, source);
+        CompiledShader {
+            pipeline: self.create_pipeline(&shader, 1),
+            multisampled_pipeline: (sample_count > 1)
+                .then(|| (sample_count, self.create_pipeline(&shader, sample_count))),
+            shader,
+        }
     }
 }
 
-struct FvRenderer {
+fn create_shader_module(device: &Device, source: String) -> ShaderModule {
+    device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("fv_shader"),
+        source: ShaderSource::Wgsl(source.into()),
+    })
+}
+
+fn create_pipeline(
+    device: &Device,
+    bind_group_layouts: &[&BindGroupLayout],
+    target_format: &ColorTargetState,
+    shader: &ShaderModule,
+    sample_count: u32,
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("fv_pipeline_layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("fv_pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            compilation_options: Default::default(),
+            targets: &[Some(target_format.clone())],
+        }),
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// A shader module and the pipelines created from it
+struct CompiledShader {
+    /// Kept so that `multisampled_pipeline` can be created again if the sample count changes
+    shader: ShaderModule,
     pipeline: RenderPipeline,
+    /// Sample count and pipeline for rendering the view with MSAA, if it is enabled
+    multisampled_pipeline: Option<(u32, RenderPipeline)>,
+}
+
+/// A pipeline being compiled in the background. The old pipeline keeps being drawn until it is
+/// ready, so editing the equation doesn't freeze the UI.
+#[cfg(not(target_arch = "wasm32"))]
+type PendingPipeline = Arc<Mutex<Option<CompiledShader>>>;
+/// There are no threads on web, so the work is split across frames instead: the shader module
+/// is created in one frame and the pipeline in the next.
+#[cfg(target_arch = "wasm32")]
+type PendingPipeline = ShaderModule;
+
+struct FvRenderer {
+    compiled: CompiledShader,
     state: RendererState,
+    probe: Option<IterationProbe>,
+    /// Renders the whole view at low resolution to count iterations, for render statistics
+    stats: Option<IterationProbe>,
+    preview: Option<JuliaPreview>,
+    comparison: Option<ComparisonRenderer>,
+    scaled: Option<ScaledRenderer>,
+    pending_pipeline: Option<PendingPipeline>,
+    /// Used to repaint once a pipeline compiled in the background is ready
+    repaint_context: Option<Context>,
 }
 
 impl FvRenderer {
+    fn new(state: RendererState, source: String, repaint_context: Option<Context>) -> Self {
+        Self {
+            compiled: state.compile(source, 1),
+            state,
+            probe: None,
+            stats: None,
+            preview: None,
+            comparison: None,
+            scaled: None,
+            pending_pipeline: None,
+            repaint_context,
+        }
+    }
+
+    /// Switch to a new shader, blocking until its pipeline is compiled
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_shader(&mut self, source: String) {
+        self.compiled = self.state.compile(source, self.sample_count());
+        self.pending_pipeline = None;
+    }
+
+    /// The sample count the view is currently rendered with
+    fn sample_count(&self) -> u32 {
+        self.compiled
+            .multisampled_pipeline
+            .as_ref()
+            .map_or(1, |(sample_count, _)| *sample_count)
+    }
+
+    /// Create the pipeline for rendering the view with MSAA if the sample count has changed. This
+    /// blocks, but is only needed when the setting changes, as pipelines compiled in the
+    /// background also include it.
+    fn set_sample_count(&mut self, sample_count: u32) {
+        if sample_count == self.sample_count() {
+            return;
+        }
+        self.compiled.multisampled_pipeline = (sample_count > 1).then(|| {
+            (
+                sample_count,
+                self.state
+                    .create_pipeline(&self.compiled.shader, sample_count),
+            )
+        });
+    }
+
+    /// Start compiling a pipeline for a new shader, replacing any that is already compiling
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_pipeline_compilation(&mut self, source: String) {
+        let pending = Arc::new(Mutex::new(None));
+        let result = Arc::clone(&pending);
+        let device = Arc::clone(&self.state.device);
+        let bind_group_layout = Arc::clone(&self.state.bind_group_layout);
+        let palette_bind_group_layout = Arc::clone(self.state.palette.bind_group_layout());
+        let target_format = self.state.target_format.clone();
+        let sample_count = self.sample_count();
+        let repaint_context = self.repaint_context.clone();
+        std::thread::spawn(move || {
+            let shader = create_shader_module(&device, source);
+            let create = |sample_count| {
+                create_pipeline(
+                    &device,
+                    &[&bind_group_layout, &palette_bind_group_layout],
+                    &target_format,
+                    &shader,
+                    sample_count,
+                )
+            };
+            let compiled = CompiledShader {
+                pipeline: create(1),
+                multisampled_pipeline: (sample_count > 1)
+                    .then(|| (sample_count, create(sample_count))),
+                shader,
+            };
+            // If a newer pipeline was requested in the meantime, nothing reads this any more
+            *result.lock().unwrap() = Some(compiled);
+            if let Some(ctx) = repaint_context {
+                ctx.request_repaint();
+            }
+        });
+        self.pending_pipeline = Some(pending);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start_pipeline_compilation(&mut self, source: String) {
+        self.pending_pipeline = Some(create_shader_module(&self.state.device, source));
+        if let Some(ctx) = &self.repaint_context {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Switch to the pending pipeline if it has finished compiling
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_pending_pipeline(&mut self) {
+        let ready = self
+            .pending_pipeline
+            .as_ref()
+            .and_then(|pending| pending.lock().unwrap().take());
+        if let Some(compiled) = ready {
+            self.compiled = compiled;
+            self.pending_pipeline = None;
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn poll_pending_pipeline(&mut self) {
+        if let Some(shader) = self.pending_pipeline.take() {
+            let sample_count = self.sample_count();
+            self.compiled = CompiledShader {
+                pipeline: self.state.create_pipeline(&shader, 1),
+                multisampled_pipeline: (sample_count > 1).then(|| {
+                    (
+                        sample_count,
+                        self.state.create_pipeline(&shader, sample_count),
+                    )
+                }),
+                shader,
+            };
+        }
+    }
+
+    fn start_probe(&mut self, queue: &Queue, uniforms: Uniforms) {
+        let probe = self
+            .probe
+            .get_or_insert_with(|| IterationProbe::new(&self.state, [1, 1]));
+        probe.start(&self.state, queue, &self.compiled.pipeline, uniforms);
+    }
+
+    fn poll_probe(&mut self) -> Option<ProbeResult> {
+        self.probe.as_mut()?.poll(&self.state)?.first().copied()
+    }
+
+    fn start_stats(&mut self, queue: &Queue, uniforms: Uniforms, size: [u32; 2]) {
+        if self
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.size() != size)
+        {
+            self.stats = None;
+        }
+        self.stats
+            .get_or_insert_with(|| IterationProbe::new(&self.state, size))
+            .start(&self.state, queue, &self.compiled.pipeline, uniforms);
+    }
+
+    fn poll_stats(&mut self) -> Option<Vec<ProbeResult>> {
+        self.stats.as_mut()?.poll(&self.state)
+    }
+
+    fn prepare_preview(&mut self, queue: &Queue, uniforms: Uniforms) {
+        self.preview
+            .get_or_insert_with(|| JuliaPreview::new(&self.state))
+            .prepare(queue, uniforms.with_flags(self.state.target_flags()));
+    }
+
+    fn paint_preview(&self, render_pass: &mut RenderPass<'_>) {
+        if let Some(preview) = &self.preview {
+            preview.paint(&self.state, &self.compiled.pipeline, render_pass);
+        }
+    }
+
+    fn prepare_comparison(&mut self, queue: &Queue, callback: &ComparisonCallback) {
+        let uniforms = callback.uniforms.with_flags(self.state.target_flags());
+        self.comparison
+            .get_or_insert_with(|| {
+                ComparisonRenderer::new(
+                    &self.state,
+                    queue,
+                    &callback.shader_data,
+                    &callback.template,
+                )
+            })
+            .prepare(
+                &self.state,
+                queue,
+                uniforms,
+                &callback.shader_data,
+                &callback.template,
+                &callback.palette,
+            );
+    }
+
+    fn paint_comparison(&self, render_pass: &mut RenderPass<'_>) {
+        if let Some(comparison) = &self.comparison {
+            comparison.paint(render_pass);
+        }
+    }
+
     fn prepare(&mut self, queue: &Queue, callback: &FvRenderCallback) {
-        if let Some(data) = &callback.shader_recompilation_options {
-            self.pipeline = self.state.generate_pipeline(data);
+        // Finish the previous compilation before starting a new one, so that the web version
+        // always takes a frame between creating the shader module and the pipeline
+        self.poll_pending_pipeline();
+        self.set_sample_count(callback.sample_count);
+        if let Some(source) = &callback.shader_source {
+            self.start_pipeline_compilation(source.clone());
         }
+        self.state.set_palette(queue, &callback.palette);
 
         queue.write_buffer(
             &self.state.uniform_buffer,
             0,
-            bytemuck::cast_slice(&[callback.uniforms]),
+            bytemuck::cast_slice(&[callback.uniforms.with_flags(self.state.target_flags())]),
         );
     }
 
-    fn paint(&self, render_pass: &mut RenderPass<'static>) {
-        render_pass.set_pipeline(&self.pipeline);
+    fn paint(&self, render_pass: &mut RenderPass<'_>) {
+        render_pass.set_pipeline(&self.compiled.pipeline);
         render_pass.set_bind_group(0, &self.state.bind_group, &[]);
+        self.state.bind_palette(render_pass);
         render_pass.draw(0..6, 0..1);
     }
+
+    fn render_scaled(&mut self, encoder: &mut CommandEncoder, size: [u32; 2]) {
+        let (sample_count, pipeline) = match &self.compiled.multisampled_pipeline {
+            Some((sample_count, pipeline)) => (*sample_count, pipeline),
+            None => (1, &self.compiled.pipeline),
+        };
+        self.scaled
+            .get_or_insert_with(|| ScaledRenderer::new(&self.state))
+            .render(&self.state, encoder, pipeline, sample_count, size);
+    }
+
+    fn paint_scaled(&self, render_pass: &mut RenderPass<'_>) {
+        if let Some(scaled) = &self.scaled {
+            scaled.paint(render_pass);
+        }
+    }
+
+    /// Render the fractal offscreen and read it back as tightly packed RGBA rows. Images larger
+    /// than a texture can be are rendered in tiles, each showing its part of the region covered
+    /// by `uniforms`. This blocks until the GPU has finished rendering, so it is only available
+    /// on native. Running out of GPU memory, e.g. for a very large screenshot, or losing the
+    /// device gives an error instead of panicking.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_to_rgba(
+        &self,
+        queue: &Queue,
+        uniforms: Uniforms,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, String> {
+        let tile_size = self
+            .state
+            .device
+            .limits()
+            .max_texture_dimension_2d
+            .min(MAX_TILE_SIZE);
+        if width <= tile_size && height <= tile_size {
+            return self.render_tile_to_rgba(queue, uniforms, width, height);
+        }
+
+        let row_bytes = width as usize * 4;
+        let mut pixels = vec![0; row_bytes * height as usize];
+        for tile_y in (0..height).step_by(tile_size as usize) {
+            for tile_x in (0..width).step_by(tile_size as usize) {
+                let tile_width = tile_size.min(width - tile_x);
+                let tile_height = tile_size.min(height - tile_y);
+                let tile = self.render_tile_to_rgba(
+                    queue,
+                    uniforms.offset(Vec2::new(tile_x as f32, tile_y as f32)),
+                    tile_width,
+                    tile_height,
+                )?;
+
+                let tile_row_bytes = tile_width as usize * 4;
+                for (row, tile_row) in tile.chunks_exact(tile_row_bytes).enumerate() {
+                    let start = (tile_y as usize + row) * row_bytes + tile_x as usize * 4;
+                    pixels[start..start + tile_row_bytes].copy_from_slice(tile_row);
+                }
+            }
+        }
+        Ok(pixels)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_tile_to_rgba(
+        &self,
+        queue: &Queue,
+        uniforms: Uniforms,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, String> {
+        let device = &self.state.device;
+        device.push_error_scope(ErrorFilter::OutOfMemory);
+        device.push_error_scope(ErrorFilter::Validation);
+        let extent = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("fv_offscreen_texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.state.target_format.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        let padded_bytes_per_row = (width * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("fv_offscreen_readback_buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        // The uniform buffer is rewritten before the next on-screen frame is drawn
+        queue.write_buffer(
+            &self.state.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniforms.with_flags(self.state.target_flags())]),
+        );
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("fv_offscreen_encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("fv_offscreen_render_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.paint(&mut render_pass);
+        }
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            extent,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let _ = device.poll(Maintain::Wait);
+
+        let validation_error = pollster::block_on(device.pop_error_scope());
+        let memory_error = pollster::block_on(device.pop_error_scope());
+        if let Some(error) = memory_error.or(validation_error) {
+            return Err(error.to_string());
+        }
+        // The map fails rather than the errors above if the device has been lost
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Couldn't read the image back from the GPU: {e}"))?;
+
+        let bgra = self.state.is_bgra();
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in data.chunks_exact(padded_bytes_per_row as usize) {
+            for pixel in row[..width as usize * 4].chunks_exact(4) {
+                if bgra {
+                    pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                } else {
+                    pixels.extend_from_slice(pixel);
+                }
+            }
+        }
+        drop(data);
+        readback_buffer.unmap();
+
+        Ok(pixels)
+    }
+}
+
+/// Parse a zoom typed as a number such as `1e12` or as a power of ten such as `10^12`, clamped to
+/// the zooms the view supports
+fn parse_zoom(text: &str) -> Result<f64, &'static str> {
+    let text = text.trim();
+    let zoom = match text.strip_prefix("10^") {
+        Some(exponent) => exponent
+            .trim()
+            .parse::<f64>()
+            .map(|exponent| 10f64.powf(exponent)),
+        None => text.parse::<f64>(),
+    }
+    .map_err(|_| "Not a number")?;
+    if zoom.is_nan() || zoom <= 0.0 {
+        return Err("Zoom must be positive");
+    }
+    if zoom.is_infinite() {
+        return Err("Zoom is too large");
+    }
+    Ok(zoom.clamp(MIN_ZOOM, MAX_ZOOM))
+}
+
+/// Report errors from `device` and its loss through `gpu_error` and `device_lost`, instead of
+/// wgpu's default of panicking
+fn watch_device(
+    device: &Device,
+    ctx: &Context,
+    gpu_error: &Arc<Mutex<Option<String>>>,
+    device_lost: &Arc<AtomicBool>,
+) {
+    {
+        let gpu_error = Arc::clone(gpu_error);
+        let device_lost = Arc::clone(device_lost);
+        let ctx = ctx.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // These are reported when the device is dropped on exit or this callback is replaced,
+            // not when the device stops working
+            if matches!(
+                reason,
+                DeviceLostReason::Dropped | DeviceLostReason::ReplacedCallback
+            ) {
+                return;
+            }
+            log::error!("GPU device lost ({reason:?}): {message}");
+            *gpu_error.lock().unwrap() = Some(format!("The GPU device was lost ({message})"));
+            device_lost.store(true, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+    {
+        let gpu_error = Arc::clone(gpu_error);
+        let ctx = ctx.clone();
+        // The default handler panics, which would close the app on e.g. a timeout
+        device.on_uncaptured_error(Box::new(move |error| {
+            log::error!("GPU error: {error}");
+            gpu_error
+                .lock()
+                .unwrap()
+                .get_or_insert(format!("GPU error: {error}"));
+            ctx.request_repaint();
+        }));
+    }
+}
+
+/// A readable name for a wgpu backend
+fn backend_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Empty => "Empty",
+        Backend::Vulkan => "Vulkan",
+        Backend::Metal => "Metal",
+        Backend::Dx12 => "DirectX 12",
+        Backend::Gl => "WebGL/OpenGL",
+        Backend::BrowserWebGpu => "WebGPU",
+        // Keeps this compiling if a later wgpu adds a backend
+        #[allow(unreachable_patterns)]
+        other => other.to_str(),
+    }
+}
+
+/// Whether the window is minimised or unfocused, or on web whether the tab is hidden
+#[cfg(not(target_arch = "wasm32"))]
+fn is_in_background(ctx: &Context) -> bool {
+    ctx.input(|i| !i.focused || i.viewport().minimized == Some(true))
+}
+
+/// Whether the window is minimised or unfocused, or on web whether the tab is hidden
+#[cfg(target_arch = "wasm32")]
+fn is_in_background(_ctx: &Context) -> bool {
+    // The canvas loses focus whenever a text field is edited, so only the page visibility is used
+    web_sys::window()
+        .and_then(|window| window.document())
+        .is_some_and(|document| document.hidden())
 }
 
 struct FvRenderCallback {
     uniforms: Uniforms,
-    shader_recompilation_options: Option<CustomShaderData>,
+    /// Size of the intermediate texture to render to, if rendering at a lower resolution
+    render_size: Option<[u32; 2]>,
+    /// Set when the shader needs recompiling
+    shader_source: Option<String>,
+    /// Uploaded only if it differs from the palette the renderer already has
+    palette: Vec<u8>,
+    /// MSAA sample count, which must be 1 unless `render_size` is set
+    sample_count: u32,
 }
 
 impl egui_wgpu::CallbackTrait for FvRenderCallback {
@@ -545,11 +3600,80 @@ impl egui_wgpu::CallbackTrait for FvRenderCallback {
         _device: &Device,
         queue: &Queue,
         _screen_descriptor: &ScreenDescriptor,
-        _egui_encoder: &mut CommandEncoder,
+        egui_encoder: &mut CommandEncoder,
         callback_resources: &mut CallbackResources,
     ) -> Vec<CommandBuffer> {
         let renderer: &mut FvRenderer = callback_resources.get_mut().unwrap();
         renderer.prepare(queue, self);
+        if let Some(size) = self.render_size {
+            renderer.render_scaled(egui_encoder, size);
+        }
+        vec![]
+    }
+
+    fn paint(
+        &self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut RenderPass<'static>,
+        callback_resources: &CallbackResources,
+    ) {
+        let renderer: &FvRenderer = callback_resources.get().unwrap();
+        if self.render_size.is_some() {
+            renderer.paint_scaled(render_pass);
+        } else {
+            renderer.paint(render_pass);
+        }
+    }
+}
+
+struct ComparisonCallback {
+    uniforms: Uniforms,
+    shader_data: CustomShaderData,
+    /// The main view's shader template, so that a shader loaded from disk is used on both sides
+    template: Arc<str>,
+    palette: Vec<u8>,
+}
+
+impl egui_wgpu::CallbackTrait for ComparisonCallback {
+    fn prepare(
+        &self,
+        _device: &Device,
+        queue: &Queue,
+        _screen_descriptor: &ScreenDescriptor,
+        _egui_encoder: &mut CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<CommandBuffer> {
+        let renderer: &mut FvRenderer = callback_resources.get_mut().unwrap();
+        renderer.prepare_comparison(queue, self);
+        vec![]
+    }
+
+    fn paint(
+        &self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut RenderPass<'static>,
+        callback_resources: &CallbackResources,
+    ) {
+        let renderer: &FvRenderer = callback_resources.get().unwrap();
+        renderer.paint_comparison(render_pass);
+    }
+}
+
+struct JuliaPreviewCallback {
+    uniforms: Uniforms,
+}
+
+impl egui_wgpu::CallbackTrait for JuliaPreviewCallback {
+    fn prepare(
+        &self,
+        _device: &Device,
+        queue: &Queue,
+        _screen_descriptor: &ScreenDescriptor,
+        _egui_encoder: &mut CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<CommandBuffer> {
+        let renderer: &mut FvRenderer = callback_resources.get_mut().unwrap();
+        renderer.prepare_preview(queue, self.uniforms);
         vec![]
     }
 
@@ -560,6 +3684,6 @@ impl egui_wgpu::CallbackTrait for FvRenderCallback {
         callback_resources: &CallbackResources,
     ) {
         let renderer: &FvRenderer = callback_resources.get().unwrap();
-        renderer.paint(render_pass);
+        renderer.paint_preview(render_pass);
     }
 }
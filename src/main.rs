@@ -1,12 +1,120 @@
 use eframe::NativeOptions;
-use fractal_viewer::FractalViewerApp;
+use egui_wgpu::wgpu;
+use fractal_viewer::{
+    run_benchmark, show_native_error, FractalViewerApp, RendererUnavailableApp, TROUBLESHOOTING,
+};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: fractal_viewer [--backend <vulkan|metal|dx12|gl>] [--power-preference <high|low>] [--shader <path to shader.wgsl>] [settings string or link]
+       fractal_viewer --benchmark [--json]";
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
-    let options = NativeOptions::default();
+
+    // A settings export string or link to open, as with the query string on web
+    let mut settings_arg = None;
+    let mut backends = None;
+    let mut power_preference = None;
+    let mut benchmark = false;
+    let mut json = false;
+    // A shader to use instead of the embedded one, reloaded when it changes
+    let mut shader_path = std::env::var_os("FRACTAL_VIEWER_SHADER").map(PathBuf::from);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--backend" => match args
+                .next()
+                .map(|s| wgpu::util::parse_backends_from_comma_list(&s))
+            {
+                Some(b) if !b.is_empty() => backends = Some(b),
+                _ => eprintln!("Unknown backend, expected vulkan, metal, dx12 or gl"),
+            },
+            "--power-preference" => match args.next().as_deref() {
+                Some("high") => power_preference = Some(wgpu::PowerPreference::HighPerformance),
+                Some("low") => power_preference = Some(wgpu::PowerPreference::LowPower),
+                _ => eprintln!("Unknown power preference, expected high or low"),
+            },
+            "--shader" => match args.next() {
+                Some(path) => shader_path = Some(PathBuf::from(path)),
+                None => eprintln!("Expected a path to a shader after --shader"),
+            },
+            "--benchmark" => benchmark = true,
+            "--json" => json = true,
+            "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => settings_arg = Some(arg),
+        }
+    }
+
+    if benchmark {
+        match run_benchmark() {
+            Ok(report) if json => println!("{}", report.to_json()),
+            Ok(report) => println!("{report}"),
+            Err(e) => {
+                eprintln!("Benchmark failed: {e}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut options = NativeOptions::default();
+    let mut adapter_note = None;
+    if let Some(backends) = backends {
+        // eframe can't be restarted with other options if creating the renderer fails, so check
+        // that the backend is available first
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        if instance.enumerate_adapters(backends).is_empty() {
+            let note = "The requested backend is not available, so the default was used instead";
+            eprintln!("{note}");
+            adapter_note = Some(note.to_string());
+        } else {
+            options.wgpu_options.supported_backends = backends;
+        }
+    }
+    if let Some(power_preference) = power_preference {
+        options.wgpu_options.power_preference = power_preference;
+    }
+
+    let result = run(options, settings_arg, adapter_note, shader_path);
+    if let Err(eframe::Error::Wgpu(e)) = &result {
+        eprintln!("Failed to start the renderer: {e}\n{TROUBLESHOOTING}");
+        // Started from a file manager there is no terminal to show the above
+        show_native_error(&e.to_string());
+    }
+    result
+}
+
+fn run(
+    options: NativeOptions,
+    settings_arg: Option<String>,
+    adapter_note: Option<String>,
+    shader_path: Option<PathBuf>,
+) -> Result<(), eframe::Error> {
     eframe::run_native(
         "fractal_viewer",
         options,
-        Box::new(|cc| Ok(Box::new(FractalViewerApp::new(cc).unwrap()))),
+        Box::new(move |cc| {
+            let Some(mut app) = FractalViewerApp::new(cc) else {
+                return Ok(Box::new(RendererUnavailableApp));
+            };
+            if let Some(settings) = &settings_arg {
+                if let Err(e) = app.import_settings(settings) {
+                    eprintln!("Failed to import settings from command line: {e}");
+                }
+            }
+            if let Some(note) = adapter_note {
+                app.set_adapter_note(note);
+            }
+            if let Some(path) = shader_path {
+                app.set_shader_path(path);
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
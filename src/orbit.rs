@@ -0,0 +1,90 @@
+use crate::settings::{FractalMode, UserSettings};
+use crate::uniforms::julia_morph;
+
+/// Most points of an orbit that are worked out and drawn
+pub(crate) const MAX_ORBIT_POINTS: usize = 1000;
+
+/// Equations the orbit can be worked out for on the CPU, matching what the shader does for them
+#[derive(Copy, Clone)]
+enum OrbitEquation {
+    Square,
+    BurningShip,
+    Tricorn,
+    Cube,
+}
+
+impl OrbitEquation {
+    fn from_settings(settings: &UserSettings) -> Option<Self> {
+        if settings.mode != FractalMode::EscapeTime
+            || !settings.shader_data.has_default_escape_condition()
+        {
+            return None;
+        }
+        let equation: String = settings
+            .shader_data
+            .equation
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        match equation.as_str() {
+            "csquare(z)+c" => Some(Self::Square),
+            "csquare(abs(z))+c" => Some(Self::BurningShip),
+            "csquare(vec2<f32>(z.x,-z.y))+c" => Some(Self::Tricorn),
+            "cmul(csquare(z),z)+c" | "cmul(z,csquare(z))+c" => Some(Self::Cube),
+            _ => None,
+        }
+    }
+
+    fn apply(self, [x, y]: [f64; 2], [cx, cy]: [f64; 2]) -> [f64; 2] {
+        let square = |[x, y]: [f64; 2]| [x * x - y * y, 2.0 * x * y];
+        let [x, y] = match self {
+            Self::Square => square([x, y]),
+            Self::BurningShip => square([x.abs(), y.abs()]),
+            Self::Tricorn => square([x, -y]),
+            Self::Cube => [x * x * x - 3.0 * x * y * y, 3.0 * x * x * y - y * y * y],
+        };
+        [x + cx, y + cy]
+    }
+}
+
+/// Whether `orbit` can work out orbits for the current equation
+pub(crate) fn supports_orbit(settings: &UserSettings) -> bool {
+    OrbitEquation::from_settings(settings).is_some()
+}
+
+/// The values z takes when iterating from `point`, up to and including the first that escapes.
+/// Returns `None` if the equation isn't one that can be iterated on the CPU.
+pub(crate) fn orbit(settings: &UserSettings, point: [f64; 2]) -> Option<Vec<[f64; 2]>> {
+    let equation = OrbitEquation::from_settings(settings)?;
+
+    let (mut z, c) = if settings.julia_set {
+        (point, settings.julia_c)
+    } else {
+        // Matches how the shader morphs towards the Julia set
+        let morph = julia_morph(settings) as f64;
+        let mix =
+            |a: [f64; 2], b: [f64; 2]| [a[0] + (b[0] - a[0]) * morph, a[1] + (b[1] - a[1]) * morph];
+        let c = mix(point, settings.julia_c);
+        let start = if settings.initial_c { c } else { [0.0, 0.0] };
+        (
+            mix(
+                [
+                    start[0] + settings.initial_value[0],
+                    start[1] + settings.initial_value[1],
+                ],
+                point,
+            ),
+            c,
+        )
+    };
+
+    let limit = (settings.iterations.max(1) as usize).min(MAX_ORBIT_POINTS);
+    let threshold = settings.escape_threshold as f64;
+    let mut points = Vec::with_capacity(limit);
+    points.push(z);
+    while points.len() < limit && (z[0] * z[0] + z[1] * z[1]).sqrt() < threshold {
+        z = equation.apply(z, c);
+        points.push(z);
+    }
+    Some(points)
+}
@@ -0,0 +1,191 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::settings::MAX_PALETTE_WIDTH;
+use egui_wgpu::wgpu;
+use std::sync::Arc;
+use wgpu::util::{DeviceExt, TextureDataOrder};
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Device,
+    Extent3d, FilterMode, Queue, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureViewDimension,
+};
+
+/// Uploaded in place of an empty palette, as textures can't be empty
+const PLACEHOLDER_PALETTE: [u8; 4] = [255; 4];
+
+/// The palette image sampled by the shader in place of the colour expression. It is bound as
+/// group 1, so that the uniform bind groups used by the preview and probes don't need to know
+/// about it.
+pub(crate) struct PaletteTexture {
+    /// Shared so that pipelines can be created on another thread
+    bind_group_layout: Arc<BindGroupLayout>,
+    sampler: Sampler,
+    bind_group: BindGroup,
+    /// The pixels currently uploaded, so that the texture is only replaced when they change
+    pixels: Vec<u8>,
+}
+
+impl PaletteTexture {
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fv_palette_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        Self::with_layout(device, queue, Arc::new(bind_group_layout))
+    }
+
+    /// A separate palette that can be bound with the same pipelines as this one
+    pub(crate) fn new_compatible(&self, device: &Device, queue: &Queue) -> Self {
+        Self::with_layout(device, queue, Arc::clone(&self.bind_group_layout))
+    }
+
+    fn with_layout(
+        device: &Device,
+        queue: &Queue,
+        bind_group_layout: Arc<BindGroupLayout>,
+    ) -> Self {
+        // Clamping keeps the ends of the palette from blending into each other
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("fv_palette_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = Self::create_bind_group(device, queue, &bind_group_layout, &sampler, &[]);
+        Self {
+            bind_group_layout,
+            sampler,
+            bind_group,
+            pixels: Vec::new(),
+        }
+    }
+
+    fn create_bind_group(
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        pixels: &[u8],
+    ) -> BindGroup {
+        let pixels: &[u8] = if pixels.is_empty() {
+            &PLACEHOLDER_PALETTE
+        } else {
+            pixels
+        };
+        // The texture is Unorm rather than sRGB, so sampling gives the same sRGB encoded values
+        // that colour expressions output
+        let texture = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("fv_palette_texture"),
+                size: Extent3d {
+                    width: (pixels.len() / 4) as u32,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            TextureDataOrder::LayerMajor,
+            pixels,
+        );
+        let view = texture.create_view(&Default::default());
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_palette_bind_group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    pub(crate) fn bind_group_layout(&self) -> &Arc<BindGroupLayout> {
+        &self.bind_group_layout
+    }
+
+    pub(crate) fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Upload a palette of RGBA pixels, if it isn't the one already uploaded
+    pub(crate) fn set(&mut self, device: &Device, queue: &Queue, pixels: &[u8]) {
+        if pixels == self.pixels {
+            return;
+        }
+        self.bind_group = Self::create_bind_group(
+            device,
+            queue,
+            &self.bind_group_layout,
+            &self.sampler,
+            pixels,
+        );
+        self.pixels = pixels.to_vec();
+    }
+}
+
+/// Read a palette from a PNG image, as a row of RGBA pixels running from the lowest iteration
+/// count to the highest. The middle row is used, so both horizontal strips and larger gradient
+/// images work. Images wider than `MAX_PALETTE_WIDTH` are resampled down to it.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn decode_palette_png(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+
+    let width = info.width as usize;
+    let row = &buffer[info.line_size * (info.height as usize / 2)..][..info.line_size];
+    let channels = info.color_type.samples();
+    let pixels: Vec<[u8; 4]> = row[..width * channels]
+        .chunks_exact(channels)
+        .map(|pixel| match *pixel {
+            [grey] => [grey, grey, grey, 255],
+            [grey, alpha] => [grey, grey, grey, alpha],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!("8 bit images have at most 4 samples per pixel"),
+        })
+        .collect();
+
+    if pixels.is_empty() {
+        return Err("The image is empty".to_string());
+    }
+    let output_width = width.min(MAX_PALETTE_WIDTH);
+    Ok((0..output_width)
+        .flat_map(|x| pixels[x * width / output_width])
+        .collect())
+}
@@ -0,0 +1,61 @@
+use crate::uniforms::Uniforms;
+use crate::RendererState;
+use bytemuck::Zeroable;
+use egui_wgpu::wgpu;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferUsages, Queue, RenderPass,
+    RenderPipeline,
+};
+
+/// Side length of the Julia set preview in points
+pub(crate) const PREVIEW_SIZE: f32 = 200.0;
+/// Maximum iterations used for the preview, so it stays cheap at high iteration counts
+pub(crate) const PREVIEW_MAX_ITERATIONS: i32 = 256;
+
+/// A second set of uniforms for drawing the Julia set preview with the main pipeline in the same
+/// frame as the main view.
+pub(crate) struct JuliaPreview {
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl JuliaPreview {
+    pub(crate) fn new(state: &RendererState) -> Self {
+        let uniform_buffer = state.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fv_preview_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[Uniforms::zeroed()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = state.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_preview_bind_group"),
+            layout: &state.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    pub(crate) fn prepare(&self, queue: &Queue, uniforms: Uniforms) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    pub(crate) fn paint(
+        &self,
+        state: &RendererState,
+        pipeline: &RenderPipeline,
+        render_pass: &mut RenderPass<'_>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        state.bind_palette(render_pass);
+        render_pass.draw(0..6, 0..1);
+    }
+}
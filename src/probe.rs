@@ -0,0 +1,208 @@
+use crate::uniforms::Uniforms;
+use crate::RendererState;
+use bytemuck::Zeroable;
+use egui_wgpu::wgpu;
+use std::sync::{Arc, Mutex};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferAsyncError, BufferDescriptor,
+    BufferUsages, Color, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer, ImageCopyTexture,
+    ImageDataLayout, LoadOp, Maintain, MapMode, Operations, Origin3d, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, StoreOp, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureUsages, TextureView,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ProbeResult {
+    pub(crate) iterations: u32,
+    pub(crate) escaped: bool,
+}
+
+/// Renders with the iteration probe flag set and reads the iteration counts back asynchronously,
+/// so it works on web as well as native.
+pub(crate) struct IterationProbe {
+    size: [u32; 2],
+    texture: Texture,
+    view: TextureView,
+    readback_buffer: Buffer,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    map_result: Arc<Mutex<Option<Result<(), BufferAsyncError>>>>,
+    pending: bool,
+    bgra: bool,
+}
+
+impl IterationProbe {
+    pub(crate) fn new(state: &RendererState, size: [u32; 2]) -> Self {
+        let device = &state.device;
+        let format = state.target_format.format;
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("fv_probe_texture"),
+            size: Self::extent(size),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("fv_probe_readback_buffer"),
+            size: Self::padded_bytes_per_row(size) as u64 * size[1] as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fv_probe_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[Uniforms::zeroed()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_probe_bind_group"),
+            layout: &state.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            size,
+            texture,
+            view,
+            readback_buffer,
+            uniform_buffer,
+            bind_group,
+            map_result: Arc::new(Mutex::new(None)),
+            pending: false,
+            bgra: state.is_bgra(),
+        }
+    }
+
+    pub(crate) fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    fn extent(size: [u32; 2]) -> Extent3d {
+        Extent3d {
+            width: size[0],
+            height: size[1],
+            depth_or_array_layers: 1,
+        }
+    }
+
+    fn padded_bytes_per_row(size: [u32; 2]) -> u32 {
+        (size[0] * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+    }
+
+    /// Start rendering the probe, unless a previous probe is still waiting to be read back. For a
+    /// single pixel probe, `uniforms` should be offset so that the point of interest is at the
+    /// centre of the top-left pixel.
+    pub(crate) fn start(
+        &mut self,
+        state: &RendererState,
+        queue: &Queue,
+        pipeline: &RenderPipeline,
+        uniforms: Uniforms,
+    ) {
+        if self.pending {
+            return;
+        }
+
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let mut encoder = state
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("fv_probe_encoder"),
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("fv_probe_render_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            state.bind_palette(&mut render_pass);
+            render_pass.draw(0..6, 0..1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(Self::padded_bytes_per_row(self.size)),
+                    rows_per_image: Some(self.size[1]),
+                },
+            },
+            Self::extent(self.size),
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let map_result = Arc::clone(&self.map_result);
+        self.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                *map_result.lock().unwrap() = Some(result);
+            });
+        self.pending = true;
+    }
+
+    /// Returns the result for each pixel of the last probe, in rows from the top left, once it
+    /// has been read back from the GPU.
+    pub(crate) fn poll(&mut self, state: &RendererState) -> Option<Vec<ProbeResult>> {
+        if !self.pending {
+            return None;
+        }
+
+        let _ = state.device.poll(Maintain::Poll);
+        let map_result = self.map_result.lock().unwrap().take()?;
+        self.pending = false;
+        map_result.ok()?;
+
+        let data = self.readback_buffer.slice(..).get_mapped_range();
+        let mut results = Vec::with_capacity(self.size[0] as usize * self.size[1] as usize);
+        for row in data.chunks_exact(Self::padded_bytes_per_row(self.size) as usize) {
+            for pixel in row[..self.size[0] as usize * 4].chunks_exact(4) {
+                let (r, g, b, a) = if self.bgra {
+                    (pixel[2], pixel[1], pixel[0], pixel[3])
+                } else {
+                    (pixel[0], pixel[1], pixel[2], pixel[3])
+                };
+                results.push(ProbeResult {
+                    iterations: u32::from_le_bytes([r, g, b, 0]),
+                    escaped: a != 0,
+                });
+            }
+        }
+        drop(data);
+        self.readback_buffer.unmap();
+
+        Some(results)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::RendererState;
+use egui_wgpu::wgpu;
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Color,
+    CommandEncoder, Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState, Operations,
+    PipelineLayoutDescriptor, PrimitiveState, RenderPass, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp,
+    TextureDescriptor, TextureDimension, TextureSampleType, TextureUsages, TextureViewDimension,
+    VertexState,
+};
+
+static BLIT_SHADER: &str = include_str!("blit.wgsl");
+
+struct Target {
+    size: [u32; 2],
+    sample_count: u32,
+    view: wgpu::TextureView,
+    /// Rendered to and resolved into `view` when multisampling
+    multisampled_view: Option<wgpu::TextureView>,
+    bind_group: BindGroup,
+}
+
+/// Renders the fractal to a texture smaller than the view and upscales it, for when the render
+/// scale is below 1. This is also used for MSAA, as the pass egui draws the view in can't be
+/// multisampled differently.
+pub(crate) struct ScaledRenderer {
+    blit_pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    target: Option<Target>,
+}
+
+impl ScaledRenderer {
+    pub(crate) fn new(state: &RendererState) -> Self {
+        let device = &state.device;
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fv_blit_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fv_blit_shader"),
+            source: ShaderSource::Wgsl(BLIT_SHADER.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fv_blit_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let blit_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("fv_blit_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(state.target_format.clone())],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("fv_blit_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            blit_pipeline,
+            bind_group_layout,
+            sampler,
+            target: None,
+        }
+    }
+
+    /// (Re)create the intermediate textures if the render size or sample count has changed
+    fn ensure_target(
+        &mut self,
+        state: &RendererState,
+        size: [u32; 2],
+        sample_count: u32,
+    ) -> &Target {
+        if self
+            .target
+            .as_ref()
+            .is_some_and(|t| t.size == size && t.sample_count == sample_count)
+        {
+            return self.target.as_ref().unwrap();
+        }
+
+        let create_texture = |label, sample_count, usage| {
+            state.device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: size[0],
+                    height: size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: TextureDimension::D2,
+                format: state.target_format.format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let view = create_texture(
+            "fv_scaled_texture",
+            1,
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        )
+        .create_view(&Default::default());
+        let multisampled_view = (sample_count > 1).then(|| {
+            create_texture(
+                "fv_multisampled_texture",
+                sample_count,
+                TextureUsages::RENDER_ATTACHMENT,
+            )
+            .create_view(&Default::default())
+        });
+        let bind_group = state.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fv_blit_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        self.target.insert(Target {
+            size,
+            sample_count,
+            view,
+            multisampled_view,
+            bind_group,
+        })
+    }
+
+    /// Render the fractal with the main uniforms into the intermediate texture. `pipeline` must
+    /// have been created with `sample_count` samples.
+    pub(crate) fn render(
+        &mut self,
+        state: &RendererState,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        sample_count: u32,
+        size: [u32; 2],
+    ) {
+        let target = self.ensure_target(state, size, sample_count);
+        // Only the resolved result is needed after a multisampled pass
+        let (view, resolve_target, store) = match &target.multisampled_view {
+            Some(multisampled_view) => (multisampled_view, Some(&target.view), StoreOp::Discard),
+            None => (&target.view, None, StoreOp::Store),
+        };
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("fv_scaled_render_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &state.bind_group, &[]);
+        state.bind_palette(&mut render_pass);
+        render_pass.draw(0..6, 0..1);
+    }
+
+    /// Draw the intermediate texture stretched over the view
+    pub(crate) fn paint(&self, render_pass: &mut RenderPass<'_>) {
+        if let Some(target) = &self.target {
+            render_pass.set_pipeline(&self.blit_pipeline);
+            render_pass.set_bind_group(0, &target.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
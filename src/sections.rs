@@ -0,0 +1,40 @@
+use eframe::egui::{CollapsingHeader, CollapsingResponse, Ui};
+use std::collections::BTreeSet;
+
+/// The titles of the collapsing sections of the settings window that are open, saved with the
+/// app's state so that they are open again after a restart
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct OpenSections(BTreeSet<String>);
+
+impl OpenSections {
+    pub(crate) fn is_open(&self, title: &str) -> bool {
+        self.0.contains(title)
+    }
+
+    /// A collapsing header that starts open if it was open last time. Its response needs passing
+    /// to `record`; this is only needed over `show` for sections with other sections inside.
+    pub(crate) fn header(&self, title: &str) -> CollapsingHeader {
+        CollapsingHeader::new(title).default_open(self.is_open(title))
+    }
+
+    /// Show a collapsing section like `Ui::collapsing`, starting open if it was open last time
+    pub(crate) fn show<R>(
+        &mut self,
+        ui: &mut Ui,
+        title: &str,
+        add_contents: impl FnOnce(&mut Ui) -> R,
+    ) -> CollapsingResponse<R> {
+        let response = self.header(title).show(ui, add_contents);
+        self.record(title, &response);
+        response
+    }
+
+    /// Remember whether a section shown with `header` is open
+    pub(crate) fn record<R>(&mut self, title: &str, response: &CollapsingResponse<R>) {
+        if response.fully_open() {
+            self.0.insert(title.to_string());
+        } else if response.fully_closed() {
+            self.0.remove(title);
+        }
+    }
+}
@@ -1,8 +1,44 @@
-pub(crate) mod v0_3 {
-    use crate::settings::{CustomShaderData, InvalidSettingsImportError};
+//! Settings layouts used by older versions, so that strings they exported can still be imported.
+//! `import` is the only way into these modules, and is what `UserSettings::import_string` falls
+//! back to for any version other than the current one.
 
-    use base64::engine::general_purpose;
-    use base64::Engine;
+use crate::settings::InvalidSettingsImportError;
+use base64::{engine::general_purpose, Engine};
+use serde::de::DeserializeOwned;
+
+/// Versions whose exported strings can still be imported, oldest first. Only the tests use the
+/// list, to check that each version has an export to try.
+#[cfg(test)]
+pub(crate) const VERSIONS: &[&str] = &["0.3", "0.4", "0.5", "2.0", "2.1"];
+
+/// Import the base64 part of a string exported by an older version. Returns `None` if `version`
+/// isn't one of `VERSIONS`.
+pub(crate) fn import(
+    version: &str,
+    base64: &str,
+) -> Option<Result<crate::settings::UserSettings, InvalidSettingsImportError>> {
+    let result = match version {
+        "0.3" => decode::<v0_3::UserSettings>(base64).map(Into::into),
+        "0.4" => decode::<v0_4::UserSettings>(base64).map(Into::into),
+        "0.5" => decode::<v0_5::UserSettings>(base64).map(Into::into),
+        "2.0" => decode::<v2_0::UserSettings>(base64).map(Into::into),
+        "2.1" => decode::<v2_1::UserSettings>(base64).map(Into::into),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Older versions exported uncompressed bincode
+fn decode<T: DeserializeOwned>(base64: &str) -> Result<T, InvalidSettingsImportError> {
+    let bytes = general_purpose::STANDARD
+        .decode(base64)
+        .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
+    bincode::deserialize(bytes.as_slice())
+        .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)
+}
+
+mod v0_3 {
+    use crate::settings::CustomShaderData;
 
     #[derive(Clone, serde::Serialize, serde::Deserialize)]
     pub(crate) struct UserSettings {
@@ -17,28 +53,19 @@ pub(crate) mod v0_3 {
         escape_threshold: f32,
     }
 
-    impl UserSettings {
-        pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
-            let bytes = general_purpose::STANDARD
-                .decode(string)
-                .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
-            let result = bincode::deserialize::<'_, Self>(bytes.as_slice())
-                .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
-            Ok(result)
-        }
-    }
-
-    impl Into<crate::settings::UserSettings> for UserSettings {
-        fn into(self) -> crate::settings::UserSettings {
-            crate::settings::UserSettings {
-                zoom: self.zoom,
-                centre: self.centre,
-                iterations: self.iterations,
-                julia_set: self.julia_set,
-                initial_value: self.initial_value,
-                escape_threshold: self.escape_threshold,
+    impl From<UserSettings> for crate::settings::UserSettings {
+        fn from(old: UserSettings) -> Self {
+            Self {
+                zoom: old.zoom as f64,
+                centre: old.centre.map(f64::from),
+                iterations: old.iterations,
+                julia_set: old.julia_set,
+                initial_value: old.initial_value.map(f64::from),
+                // Older versions used the initial value as c for Julia sets
+                julia_c: old.initial_value.map(f64::from),
+                escape_threshold: old.escape_threshold,
                 shader_data: CustomShaderData {
-                    equation: self.equation,
+                    equation: old.equation,
                     ..Default::default()
                 },
                 ..Default::default()
@@ -47,11 +74,8 @@ pub(crate) mod v0_3 {
     }
 }
 
-pub(crate) mod v0_4 {
-    use crate::settings::{CustomShaderData, InvalidSettingsImportError};
-
-    use base64::engine::general_purpose;
-    use base64::Engine;
+mod v0_4 {
+    use crate::settings::CustomShaderData;
 
     #[derive(Clone, serde::Serialize, serde::Deserialize)]
     pub(crate) struct UserSettings {
@@ -70,31 +94,22 @@ pub(crate) mod v0_4 {
         escape_threshold: f32,
     }
 
-    impl UserSettings {
-        pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
-            let bytes = general_purpose::STANDARD
-                .decode(string)
-                .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
-            let result = bincode::deserialize::<'_, Self>(bytes.as_slice())
-                .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
-            Ok(result)
-        }
-    }
-
-    impl Into<crate::settings::UserSettings> for UserSettings {
-        fn into(self) -> crate::settings::UserSettings {
-            crate::settings::UserSettings {
-                zoom: self.zoom,
-                centre: self.centre,
-                iterations: self.iterations,
-                julia_set: self.julia_set,
-                smoothen: self.smoothen,
-                internal_black: self.internal_black,
-                initial_value: self.initial_value,
-                escape_threshold: self.escape_threshold,
+    impl From<UserSettings> for crate::settings::UserSettings {
+        fn from(old: UserSettings) -> Self {
+            Self {
+                zoom: old.zoom as f64,
+                centre: old.centre.map(f64::from),
+                iterations: old.iterations,
+                julia_set: old.julia_set,
+                smoothen: old.smoothen,
+                internal_black: old.internal_black,
+                initial_value: old.initial_value.map(f64::from),
+                // Older versions used the initial value as c for Julia sets
+                julia_c: old.initial_value.map(f64::from),
+                escape_threshold: old.escape_threshold,
                 shader_data: CustomShaderData {
-                    equation: self.equation,
-                    colour: self.colour,
+                    equation: old.equation,
+                    colour: old.colour,
                     ..Default::default()
                 },
                 ..Default::default()
@@ -103,11 +118,8 @@ pub(crate) mod v0_4 {
     }
 }
 
-pub(crate) mod v0_5 {
-    use crate::settings::{CustomShaderData, InvalidSettingsImportError};
-
-    use base64::engine::general_purpose;
-    use base64::Engine;
+mod v0_5 {
+    use crate::settings::CustomShaderData;
 
     #[derive(Clone, serde::Serialize, serde::Deserialize)]
     pub(crate) struct UserSettings {
@@ -127,32 +139,23 @@ pub(crate) mod v0_5 {
         initial_c: bool,
     }
 
-    impl UserSettings {
-        pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
-            let bytes = general_purpose::STANDARD
-                .decode(string)
-                .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
-            let result = bincode::deserialize::<'_, Self>(bytes.as_slice())
-                .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
-            Ok(result)
-        }
-    }
-
-    impl Into<crate::settings::UserSettings> for UserSettings {
-        fn into(self) -> crate::settings::UserSettings {
-            crate::settings::UserSettings {
-                zoom: self.zoom,
-                centre: self.centre,
-                iterations: self.iterations,
-                julia_set: self.julia_set,
-                smoothen: self.smoothen,
-                internal_black: self.internal_black,
-                initial_value: self.initial_value,
-                escape_threshold: self.escape_threshold,
-                initial_c: self.initial_c,
+    impl From<UserSettings> for crate::settings::UserSettings {
+        fn from(old: UserSettings) -> Self {
+            Self {
+                zoom: old.zoom as f64,
+                centre: old.centre.map(f64::from),
+                iterations: old.iterations,
+                julia_set: old.julia_set,
+                smoothen: old.smoothen,
+                internal_black: old.internal_black,
+                initial_value: old.initial_value.map(f64::from),
+                // Older versions used the initial value as c for Julia sets
+                julia_c: old.initial_value.map(f64::from),
+                escape_threshold: old.escape_threshold,
+                initial_c: old.initial_c,
                 shader_data: CustomShaderData {
-                    equation: self.equation,
-                    colour: self.colour,
+                    equation: old.equation,
+                    colour: old.colour,
                     ..Default::default()
                 },
                 ..Default::default()
@@ -161,11 +164,8 @@ pub(crate) mod v0_5 {
     }
 }
 
-pub(crate) mod v2_0 {
-    use crate::settings::{CustomShaderData, InvalidSettingsImportError};
-
-    use base64::engine::general_purpose;
-    use base64::Engine;
+mod v2_0 {
+    use crate::settings::CustomShaderData;
 
     #[derive(Clone, serde::Serialize, serde::Deserialize)]
     pub(crate) struct UserSettings {
@@ -182,32 +182,73 @@ pub(crate) mod v2_0 {
         initial_c: bool,
     }
 
-    impl UserSettings {
-        pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
-            let bytes = general_purpose::STANDARD
-                .decode(string)
-                .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
-            let result = bincode::deserialize::<'_, Self>(bytes.as_slice())
-                .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
-            Ok(result)
+    impl From<UserSettings> for crate::settings::UserSettings {
+        fn from(old: UserSettings) -> Self {
+            Self {
+                zoom: old.zoom as f64,
+                centre: old.centre.map(f64::from),
+                iterations: old.iterations,
+                julia_set: old.julia_set,
+                smoothen: old.smoothen,
+                internal_black: old.internal_black,
+                initial_value: old.initial_value.map(f64::from),
+                // Older versions used the initial value as c for Julia sets
+                julia_c: old.initial_value.map(f64::from),
+                escape_threshold: old.escape_threshold,
+                initial_c: old.initial_c,
+                shader_data: CustomShaderData {
+                    equation: old.equation,
+                    colour: old.colour,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
         }
     }
+}
+
+mod v2_1 {
+    use crate::settings::CustomShaderData;
+
+    #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    pub(crate) struct ShaderData {
+        equation: String,
+        colour: String,
+        additional: String,
+    }
+
+    #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    pub(crate) struct UserSettings {
+        zoom: f32,
+        centre: [f32; 2],
+        iterations: i32,
+        julia_set: bool,
+        smoothen: bool,
+        internal_black: bool,
+        initial_value: [f32; 2],
+        escape_threshold: f32,
+        initial_c: bool,
+        shader_data: ShaderData,
+    }
 
-    impl Into<crate::settings::UserSettings> for UserSettings {
-        fn into(self) -> crate::settings::UserSettings {
-            crate::settings::UserSettings {
-                zoom: self.zoom,
-                centre: self.centre,
-                iterations: self.iterations,
-                julia_set: self.julia_set,
-                smoothen: self.smoothen,
-                internal_black: self.internal_black,
-                initial_value: self.initial_value,
-                escape_threshold: self.escape_threshold,
-                initial_c: self.initial_c,
+    impl From<UserSettings> for crate::settings::UserSettings {
+        fn from(old: UserSettings) -> Self {
+            Self {
+                zoom: old.zoom as f64,
+                centre: old.centre.map(f64::from),
+                iterations: old.iterations,
+                julia_set: old.julia_set,
+                smoothen: old.smoothen,
+                internal_black: old.internal_black,
+                initial_value: old.initial_value.map(f64::from),
+                // Older versions used the initial value as c for Julia sets
+                julia_c: old.initial_value.map(f64::from),
+                escape_threshold: old.escape_threshold,
+                initial_c: old.initial_c,
                 shader_data: CustomShaderData {
-                    equation: self.equation,
-                    colour: self.colour,
+                    equation: old.shader_data.equation,
+                    colour: old.shader_data.colour,
+                    additional: old.shader_data.additional,
                     ..Default::default()
                 },
                 ..Default::default()
@@ -1,8 +1,22 @@
 mod compat;
+#[cfg(test)]
+mod tests;
 
-use crate::SHADER;
+use crate::uniforms::{calculate_scale, effective_iterations};
 use base64::{engine::general_purpose, Engine};
+use eframe::egui::Vec2;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::ops::{Range, RangeInclusive};
+
+/// Widest palette image kept in the settings, so that exported strings stay a reasonable length
+pub(crate) const MAX_PALETTE_WIDTH: usize = 1024;
+
+/// View size used to work out the region of the complex plane shown in `UserSettings::describe`
+const DESCRIBE_REFERENCE_SIZE: Vec2 = Vec2::new(1920.0, 1080.0);
 
 #[derive(Debug, serde::Deserialize)]
 pub enum InvalidSettingsImportError {
@@ -10,6 +24,9 @@ pub enum InvalidSettingsImportError {
     VersionMismatch,
     InvalidBase64,
     DeserialisationFailed,
+    InvalidQueryParameter,
+    NoImageMetadata,
+    OutOfRange,
 }
 
 impl InvalidSettingsImportError {
@@ -19,6 +36,15 @@ impl InvalidSettingsImportError {
             InvalidSettingsImportError::VersionMismatch => "Version mismatch or invalid format",
             InvalidSettingsImportError::InvalidBase64 => "Base64 decoding failed",
             InvalidSettingsImportError::DeserialisationFailed => "Deserialising data failed",
+            InvalidSettingsImportError::InvalidQueryParameter => {
+                "Unknown or invalid parameter (expected re, im, zoom or iter)"
+            }
+            InvalidSettingsImportError::NoImageMetadata => {
+                "The image doesn't contain fractal_viewer settings (only its own screenshots do)"
+            }
+            InvalidSettingsImportError::OutOfRange => {
+                "The settings contain an invalid value, such as an infinite zoom"
+            }
         }
     }
 }
@@ -44,47 +70,641 @@ fn get_major_minor_version() -> String {
     )
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
+/// Marks an export string whose data is deflate compressed, e.g. `2.2;z;<base64>`. Strings from
+/// older versions are never compressed.
+const COMPRESSED_MARKER: &str = "z";
+
+pub(crate) const STANDARD_EQUATION: &str = "csquare(z) + c";
+
+/// Derivative of the standard equation, only used in Newton mode
+pub(crate) const STANDARD_DERIVATIVE: &str = "2.0 * z";
+
+/// f(z) = z^3 - 1, the classic Newton fractal
+pub(crate) const NEWTON_EQUATION: &str = "cmul(csquare(z), z) - vec2<f32>(1.0, 0.0)";
+pub(crate) const NEWTON_DERIVATIVE: &str = "3.0 * csquare(z)";
+
+/// Maximum number of roots that can be coloured in Newton mode
+pub(crate) const MAX_ROOTS: usize = 8;
+
+pub(crate) const DEFAULT_COLOUR: &str =
+    "hsv_rgb(vec3(log(n + 1.0) / log(f32(uniforms.iterations) + 1.0), 0.8, 0.8))";
+
+/// Built in colour expressions that work with any equation, as a name and the expression
+pub(crate) const COLOUR_EXPRESSION_PRESETS: &[(&str, &str)] = &[
+    ("HSV rainbow", DEFAULT_COLOUR),
+    ("HSL rainbow", "hsl_rgb(vec3(log_scale(n), 0.8, 0.55))"),
+    (
+        "Classic blue and gold",
+        "mix(vec3(0.0, 0.03, 0.4), vec3(1.0, 0.8, 0.0), 0.5 + 0.5 * sin(18.8496 * log_scale(n)))",
+    ),
+    ("Greyscale", "vec3(log_scale(n))"),
+    (
+        "Fire",
+        "clamp(vec3(3.0, 1.5, 0.5) * log_scale(n) - vec3(0.0, 0.5, 0.9), vec3(0.0), vec3(1.0))",
+    ),
+    (
+        "Oklab rainbow",
+        "oklab_rgb(vec3(0.75, 0.13 * cos(6.28318 * log_scale(n)), 0.13 * sin(6.28318 * log_scale(n))))",
+    ),
+    (
+        "Oklab blue to gold",
+        "oklab_rgb(mix(vec3(0.25, 0.0, -0.15), vec3(0.9, 0.05, 0.15), log_scale(n)))",
+    ),
+];
+
+/// Interior colour expression that fills the inside of the set with `UserSettings::interior_colour`,
+/// or colours it by period when period colouring is on
+pub(crate) const DEFAULT_INTERIOR_COLOUR: &str = "period_colour(period)";
+
+/// Escape condition that stops iterating once |z| reaches the escape threshold
+pub(crate) const DEFAULT_ESCAPE_CONDITION: &str = "length(z) >= threshold";
+
+/// Keyword of the PNG text chunk that screenshots store their settings export string in
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const PNG_SETTINGS_KEYWORD: &str = "fractal_viewer";
+
+/// Colour expression for distance estimation mode, which shades by the distance to the set
+pub(crate) const DISTANCE_ESTIMATION_COLOUR: &str = "vec3(clamp(sqrt(de / 8.0), 0.0, 1.0))";
+
+/// Colour expression for orbit traps, giving a metallic look
+pub(crate) const ORBIT_TRAP_COLOUR: &str =
+    "vec3(1.0, 0.8, 0.5) * (1.0 - clamp(sqrt(trap), 0.0, 1.0))";
+
+/// Range of powers offered for multibrot sets. Smooth colouring needs powers above 1.
+pub(crate) const MULTIBROT_POWERS: RangeInclusive<f32> = 1.1..=16.0;
+
+/// The equation for the multibrot set z^power + c. Powers are rounded to two decimal places.
+/// Whole powers use repeated multiplication, which is more precise than `cpow` and has no branch
+/// cut; other powers use `cpow` on the principal branch, which shows up as a seam along the
+/// negative real axis that is part of those sets' usual appearance.
+pub(crate) fn multibrot_equation(power: f32) -> String {
+    let power =
+        (power.clamp(*MULTIBROT_POWERS.start(), *MULTIBROT_POWERS.end()) * 100.0).round() / 100.0;
+    if power == 2.0 {
+        // Keeps high precision mode and the interior check, which only know this equation
+        STANDARD_EQUATION.to_string()
+    } else if power.fract() == 0.0 {
+        format!("cpowi(z, {}) + c", power as i32)
+    } else {
+        format!("cpow(z, {power}) + c")
+    }
+}
+
+/// One of the built in equations, with a colour expression suited to how it escapes
+pub(crate) struct EquationPreset {
+    pub(crate) name: &'static str,
+    pub(crate) equation: &'static str,
+    pub(crate) colour: &'static str,
+}
+
+pub(crate) const EQUATION_PRESETS: &[EquationPreset] = &[
+    EquationPreset {
+        name: "Mandelbrot set",
+        equation: STANDARD_EQUATION,
+        colour: DEFAULT_COLOUR,
+    },
+    EquationPreset {
+        name: "Burning ship fractal",
+        equation: "csquare(abs(z)) + c",
+        colour: "vec3(1.0, 0.45, 0.1) * pow(log(n + 1.0) / log(f32(uniforms.iterations) + 1.0), 0.6)",
+    },
+    EquationPreset {
+        name: "Feather fractal",
+        equation: "cdiv(cmul(csquare(z), z), vec2<f32>(1.0, 0.0) + z * z) + c",
+        colour: "hsv_rgb(vec3(fract(0.55 + log(n + 1.0) / log(f32(uniforms.iterations) + 1.0)), 0.6, 0.9))",
+    },
+    EquationPreset {
+        name: "Tricorn fractal",
+        equation: "csquare(vec2<f32>(z.x, -z.y)) + c",
+        colour: "hsv_rgb(vec3(fract(0.75 + 2.0 * log(n + 1.0) / log(f32(uniforms.iterations) + 1.0)), 0.7, 0.9))",
+    },
+    // These escape within a few iterations almost everywhere, so colour by the raw count
+    EquationPreset {
+        name: "Exponential fractal",
+        equation: "cexp(z) + c",
+        colour: "hsv_rgb(vec3(fract(n / 12.0), 0.8, 0.9))",
+    },
+    EquationPreset {
+        name: "Sine fractal",
+        equation: "csin(z) + c",
+        colour: "hsv_rgb(vec3(fract(0.3 + n / 12.0), 0.8, 0.9))",
+    },
+];
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub(crate) struct CustomShaderData {
     pub(crate) equation: String,
     pub(crate) colour: String,
     pub(crate) additional: String,
+    /// Derivative of `equation` with respect to z, used in Newton mode
+    pub(crate) derivative: String,
+    /// Colour expression for the inside of the set when it is filled
+    pub(crate) interior_colour: String,
+    /// Boolean expression for whether the orbit has escaped
+    pub(crate) escape_condition: String,
+}
+
+/// One of the user-editable parts of the shader
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ShaderInput {
+    Equation,
+    Colour,
+    Derivative,
+    InteriorColour,
+    EscapeCondition,
+    Additional,
 }
 
+impl Display for ShaderInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ShaderInput::Equation => "equation",
+            ShaderInput::Colour => "colour expression",
+            ShaderInput::Derivative => "derivative",
+            ShaderInput::InteriorColour => "interior colour expression",
+            ShaderInput::EscapeCondition => "escape condition",
+            ShaderInput::Additional => "additional code",
+        })
+    }
+}
+
+const PLACEHOLDERS: [(&str, ShaderInput); 5] = [
+    ("REPLACE_FRACTAL_EQN", ShaderInput::Equation),
+    ("REPLACE_COLOR", ShaderInput::Colour),
+    ("REPLACE_DERIVATIVE", ShaderInput::Derivative),
+    ("REPLACE_INTERIOR_COLOR", ShaderInput::InteriorColour),
+    ("REPLACE_ESCAPE_CONDITION", ShaderInput::EscapeCondition),
+];
+
 impl CustomShaderData {
-    pub(crate) fn shader(&self) -> String {
-        SHADER
-            .replace("REPLACE_FRACTAL_EQN", &self.equation)
-            .replace("REPLACE_COLOR", &self.colour)
-            + &self.additional
+    /// Build the shader by inserting the user inputs into `template`, which is the embedded shader
+    /// unless one was loaded from disk
+    pub(crate) fn shader(&self, template: &str) -> String {
+        self.shader_with_inputs(template).0
     }
+
+    /// Build the shader, also returning where in it each user input was inserted so that errors
+    /// can be mapped back to what the user typed
+    pub(crate) fn shader_with_inputs(
+        &self,
+        template: &str,
+    ) -> (String, Vec<(Range<usize>, ShaderInput)>) {
+        let mut source = String::with_capacity(template.len());
+        let mut inputs = Vec::new();
+        let mut rest = template;
+
+        while let Some((index, placeholder, input)) = PLACEHOLDERS
+            .iter()
+            .filter_map(|&(placeholder, input)| {
+                rest.find(placeholder)
+                    .map(|index| (index, placeholder, input))
+            })
+            .min_by_key(|&(index, ..)| index)
+        {
+            // The inputs are expressions, which can span several lines, so they are wrapped in
+            // parentheses to keep them self-contained wherever they are substituted
+            source.push_str(&rest[..index]);
+            source.push('(');
+            let start = source.len();
+            source.push_str(self.input(input));
+            inputs.push((start..source.len(), input));
+            source.push(')');
+            rest = &rest[index + placeholder.len()..];
+        }
+        source.push_str(rest);
+
+        let start = source.len();
+        source.push_str(&self.additional);
+        inputs.push((start..source.len(), ShaderInput::Additional));
+
+        (source, inputs)
+    }
+
+    pub(crate) fn input(&self, input: ShaderInput) -> &str {
+        match input {
+            ShaderInput::Equation => &self.equation,
+            ShaderInput::Colour => &self.colour,
+            ShaderInput::Derivative => &self.derivative,
+            ShaderInput::InteriorColour => &self.interior_colour,
+            ShaderInput::EscapeCondition => &self.escape_condition,
+            ShaderInput::Additional => &self.additional,
+        }
+    }
+
+    /// Whether the inside of the set is a solid colour when period colouring is off, so it doesn't
+    /// matter where orbits end up
+    pub(crate) fn has_solid_interior(&self) -> bool {
+        self.interior_colour.trim() == DEFAULT_INTERIOR_COLOUR
+    }
+
+    /// Whether orbits escape when |z| reaches the escape threshold, which high precision mode, the
+    /// interior check and the orbit overlay assume
+    pub(crate) fn has_default_escape_condition(&self) -> bool {
+        self.escape_condition.trim() == DEFAULT_ESCAPE_CONDITION
+    }
+
+    /// Whether the equation is the standard z^2 + c, which is all high precision mode supports
+    pub(crate) fn is_standard_equation(&self) -> bool {
+        self.equation.trim() == STANDARD_EQUATION
+    }
+
+    /// The power of z in the equation, if it is one of the z^p + c family, for smooth colouring
+    pub(crate) fn equation_power(&self) -> Option<f32> {
+        let equation: String = self
+            .equation
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let term = equation.strip_suffix("+c")?;
+        match term {
+            "csquare(z)" | "csquare(abs(z))" | "csquare(vec2<f32>(z.x,-z.y))" => Some(2.0),
+            "cmul(csquare(z),z)" | "cmul(z,csquare(z))" => Some(3.0),
+            _ => term
+                .strip_prefix("cpow(z,")
+                .or_else(|| term.strip_prefix("cpowi(z,"))?
+                .strip_suffix(')')?
+                .parse()
+                .ok(),
+        }
+    }
+
+    /// Distance estimation relies on the equation being holomorphic, which rules out equations
+    /// using `abs` like the burning ship fractal
+    pub(crate) fn supports_distance_estimation(&self) -> bool {
+        !self.equation.contains("abs(")
+    }
+
+    /// Whether the equation is one of the holomorphic z^p + c equations, whose derivative p z^(p-1)
+    /// is known exactly
+    pub(crate) fn is_holomorphic_power(&self) -> bool {
+        self.equation_power().is_some()
+            && self.supports_distance_estimation()
+            && !self.equation.contains("-z.y")
+    }
+
+    /// Morphing to a Julia set only makes sense for the holomorphic z^p + c equations, whose
+    /// Julia sets correspond to points of the Mandelbrot set
+    pub(crate) fn supports_julia_morph(&self) -> bool {
+        self.is_holomorphic_power()
+    }
+}
+
+/// A named colour setup which can be applied independently of the equation and view
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ColourPreset {
+    pub(crate) name: String,
+    pub(crate) colour: String,
+    pub(crate) internal_black: bool,
+    pub(crate) interior_colour: [u8; 3],
+    pub(crate) interior_colour_expression: String,
+    pub(crate) smoothen: bool,
+    pub(crate) smoothing_strength: f32,
+    pub(crate) smoothing_power: f32,
+    pub(crate) invert_colours: InvertColours,
+    pub(crate) use_palette: bool,
+    /// Palette image as a row of RGBA pixels, like `UserSettings::palette`
+    pub(crate) palette: Vec<u8>,
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-pub(crate) struct UserSettings {
-    pub(crate) zoom: f32,
-    pub(crate) centre: [f32; 2],
+impl ColourPreset {
+    pub(crate) fn from_settings(name: String, settings: &UserSettings) -> Self {
+        Self {
+            name,
+            colour: settings.shader_data.colour.clone(),
+            internal_black: settings.internal_black,
+            interior_colour: settings.interior_colour,
+            interior_colour_expression: settings.shader_data.interior_colour.clone(),
+            smoothen: settings.smoothen,
+            smoothing_strength: settings.smoothing_strength,
+            smoothing_power: settings.smoothing_power,
+            invert_colours: settings.invert_colours,
+            use_palette: settings.use_palette,
+            palette: settings.palette.clone(),
+        }
+    }
+
+    /// Apply only the colour-related fields of this preset to `settings`
+    pub(crate) fn apply(&self, settings: &mut UserSettings) {
+        settings.shader_data.colour = self.colour.clone();
+        settings.internal_black = self.internal_black;
+        settings.interior_colour = self.interior_colour;
+        settings.shader_data.interior_colour = self.interior_colour_expression.clone();
+        settings.smoothen = self.smoothen;
+        settings.smoothing_strength = self.smoothing_strength;
+        settings.smoothing_power = self.smoothing_power;
+        settings.invert_colours = self.invert_colours;
+        settings.use_palette = self.use_palette;
+        settings.palette = self.palette.clone();
+    }
+
+    pub(crate) fn export_string(&self) -> String {
+        let encoded = bincode::serialize(self).unwrap();
+        format!("colour;{}", general_purpose::STANDARD.encode(encoded))
+    }
+
+    pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
+        let base64 = string
+            .trim()
+            .strip_prefix("colour;")
+            .ok_or(InvalidSettingsImportError::InvalidFormat)?;
+        let bytes = general_purpose::STANDARD
+            .decode(base64)
+            .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
+        let preset = bincode::deserialize::<'_, Self>(bytes.as_slice())
+            .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
+        // Checked like a palette in imported settings
+        if preset.palette.len().is_multiple_of(4) && preset.palette.len() <= MAX_PALETTE_WIDTH * 4 {
+            Ok(preset)
+        } else {
+            Err(InvalidSettingsImportError::OutOfRange)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum FractalMode {
+    /// Colour by how many iterations it takes to escape, for Mandelbrot and Julia sets
+    #[default]
+    EscapeTime,
+    /// Run Newton's method on the equation and colour by which root it converges to
+    Newton,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum FrameRateLimit {
+    #[default]
+    Uncapped,
+    Fps60,
+    Fps30,
+    /// Only repaint in response to input, apart from effects like auto zoom which are limited to
+    /// 30 FPS
+    OnDemand,
+}
+
+impl FrameRateLimit {
+    /// Minimum time between frames while something is animating, if limited
+    pub(crate) fn frame_interval(&self) -> Option<std::time::Duration> {
+        match self {
+            FrameRateLimit::Uncapped => None,
+            FrameRateLimit::Fps60 => Some(std::time::Duration::from_secs_f64(1.0 / 60.0)),
+            FrameRateLimit::Fps30 | FrameRateLimit::OnDemand => {
+                Some(std::time::Duration::from_secs_f64(1.0 / 30.0))
+            }
+        }
+    }
+}
+
+impl Display for FrameRateLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameRateLimit::Uncapped => write!(f, "Uncapped"),
+            FrameRateLimit::Fps60 => write!(f, "60 FPS"),
+            FrameRateLimit::Fps30 => write!(f, "30 FPS"),
+            FrameRateLimit::OnDemand => write!(f, "On demand"),
+        }
+    }
+}
+
+/// Which dimension of the view `zoom` fixes the extent of in the complex plane
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum Framing {
+    /// The shorter side spans `4 / zoom`, so the whole region fits whichever way round the view is
+    #[default]
+    ShorterSide,
+    /// The width spans `4 / zoom` and the height follows from the aspect ratio, so screenshots at
+    /// any size show the same horizontal region as the window
+    Width,
+}
+
+/// How the palette is inverted, if at all
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum InvertColours {
+    #[default]
+    Off,
+    /// Count iterations down from the limit, so gradients run the other way
+    Iterations,
+    /// Subtract the colour from white, giving a negative
+    Rgb,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum OrbitTrapShape {
+    #[default]
+    None = 0,
+    Point = 1,
+    Line = 2,
+    Cross = 3,
+}
+
+/// A shape that the colour expression can measure the orbit's closest approach to, as `trap`
+#[derive(Copy, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub(crate) struct OrbitTrap {
+    pub(crate) shape: OrbitTrapShape,
+    pub(crate) centre: [f64; 2],
+    /// Angle of the line, or of one arm of the cross, in radians
+    pub(crate) angle: f32,
+}
+
+// `serde(default)` has no effect on bincode, but lets bookmarks saved as JSON by older versions
+// load after new fields are added
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub(crate) zoom: f64,
+    pub(crate) centre: [f64; 2],
     pub(crate) iterations: i32,
     pub(crate) julia_set: bool,
     pub(crate) smoothen: bool,
+    /// Fill the inside of the set with `interior_colour` instead of using the colour expression
     pub(crate) internal_black: bool,
-    pub(crate) initial_value: [f32; 2],
+    /// sRGB colour for the inside of the set
+    pub(crate) interior_colour: [u8; 3],
+    pub(crate) initial_value: [f64; 2],
+    /// Value of c for Julia sets, kept separately so switching to and from the Mandelbrot set
+    /// preserves both
+    pub(crate) julia_c: [f64; 2],
     pub(crate) escape_threshold: f32,
     pub(crate) initial_c: bool,
     pub(crate) shader_data: CustomShaderData,
+    pub(crate) high_precision: bool,
+    pub(crate) distance_estimation: bool,
+    /// Supersampling factor; each pixel is sampled `samples * samples` times
+    pub(crate) samples: u32,
+    /// Power used by the multibrot control, which sets the equation to z^power + c
+    pub(crate) multibrot_power: f32,
+    /// Smooth the edge of the filled inside of the set by blending it with the outside, working out
+    /// fewer colours than supersampling
+    pub(crate) edge_antialiasing: bool,
+    pub(crate) mode: FractalMode,
+    /// Roots of the equation to colour by in Newton mode
+    pub(crate) roots: Vec<[f64; 2]>,
+    pub(crate) orbit_trap: OrbitTrap,
+    /// Colour by sampling `palette` instead of with the colour expression
+    pub(crate) use_palette: bool,
+    /// Palette image as a row of RGBA pixels, at most `MAX_PALETTE_WIDTH` wide
+    pub(crate) palette: Vec<u8>,
+    /// How far smooth colouring blends from banded to fully smooth, from 0 to 1
+    pub(crate) smoothing_strength: f32,
+    /// Power of z used for smooth colouring when it can't be worked out from the equation
+    pub(crate) smoothing_power: f32,
+    /// Skip iterating points that are known to be inside the Mandelbrot set
+    pub(crate) interior_check: bool,
+    /// Colour the inside of the Mandelbrot set by the period its orbits settle into
+    pub(crate) period_colouring: bool,
+    /// Resolution to render at as a fraction of the view's, upscaled to fill it
+    pub(crate) render_scale: f32,
+    pub(crate) frame_rate_limit: FrameRateLimit,
+    /// Multiplier for how far each scroll wheel step zooms
+    pub(crate) scroll_sensitivity: f32,
+    /// Zoom in when scrolling down instead of up
+    pub(crate) invert_scroll: bool,
+    /// Keep the view moving for a moment after a drag is released
+    pub(crate) pan_inertia: bool,
+    /// Draw the axes and a grid over the fractal
+    pub(crate) show_grid: bool,
+    pub(crate) invert_colours: InvertColours,
+    pub(crate) framing: Framing,
+    /// Change the zoom with the size of the view so each pixel covers the same distance
+    pub(crate) keep_scale_on_resize: bool,
+    /// How far the Mandelbrot set is morphed towards the Julia set for `julia_c`, from 0 to 1
+    pub(crate) julia_morph: f32,
+    /// Add more iterations the further the view is zoomed in, on top of `iterations`
+    pub(crate) auto_iterations: bool,
+    /// Iterations added each time the zoom doubles when `auto_iterations` is on
+    pub(crate) auto_iterations_rate: f64,
+    /// Upper limit for the iteration count when `auto_iterations` is on
+    pub(crate) auto_iterations_limit: i32,
 }
 
 impl UserSettings {
-    pub(crate) fn export_string(&self) -> String {
+    /// A human-readable summary of the view, for checking what an export contains or logging
+    /// what was loaded. The bounds are for a 1920x1080 view, as the real size depends on the
+    /// window.
+    pub fn describe(&self) -> String {
+        let equation = self
+            .shader_data
+            .equation
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let equation = match self.mode {
+            FractalMode::EscapeTime if self.julia_set => format!(
+                "z = {equation} (Julia set with c = {} + {}i)",
+                self.julia_c[0], self.julia_c[1]
+            ),
+            FractalMode::EscapeTime => format!("z = {equation}"),
+            FractalMode::Newton => format!("Newton's method on f(z) = {equation}"),
+        };
+
+        let scale = calculate_scale(DESCRIBE_REFERENCE_SIZE, self);
+        let half_width = DESCRIBE_REFERENCE_SIZE.x as f64 / 2.0 * scale;
+        let half_height = DESCRIBE_REFERENCE_SIZE.y as f64 / 2.0 * scale;
+
+        format!(
+            "Centre: {} + {}i\n\
+            Zoom: {}x\n\
+            Iterations: {}\n\
+            Equation: {equation}\n\
+            Bounds at {}x{}: {} to {} (real), {} to {} (imaginary)",
+            self.centre[0],
+            self.centre[1],
+            self.zoom,
+            effective_iterations(self),
+            DESCRIBE_REFERENCE_SIZE.x,
+            DESCRIBE_REFERENCE_SIZE.y,
+            self.centre[0] - half_width,
+            self.centre[0] + half_width,
+            self.centre[1] - half_height,
+            self.centre[1] + half_height,
+        )
+    }
+
+    /// A default file name describing the view, e.g. `fractal_z1.2e5_c-0.75+0.1i.png`, so that a
+    /// folder of screenshots sorts and can be told apart at a glance. The centre is given to more
+    /// decimal places the deeper the zoom.
+    pub(crate) fn file_name(&self, extension: &str) -> String {
+        let decimals = (self.zoom.log10().max(0.0) as usize + 2).min(15);
+        let format = |x: f64| {
+            let formatted = format!("{x:.decimals$}");
+            let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+            match trimmed {
+                "" | "-" | "-0" => "0".to_string(),
+                trimmed => trimmed.to_string(),
+            }
+        };
+        let imaginary = format(self.centre[1]);
+        let sign = if imaginary.starts_with('-') { "" } else { "+" };
+        let name = format!(
+            "fractal_z{:.1e}_c{}{sign}{imaginary}i.{extension}",
+            self.zoom,
+            format(self.centre[0]),
+        );
+        // Only keep characters that are safe in file names on every platform
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "+-._".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    /// Encode the settings as a string that `import_string` reads back, for sharing them
+    pub fn export_string(&self) -> String {
         let encoded = bincode::serialize(self).unwrap();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&encoded).unwrap();
         format!(
-            "{};{}",
+            "{};{COMPRESSED_MARKER};{}",
             get_major_minor_version(),
-            general_purpose::STANDARD.encode(encoded)
+            general_purpose::STANDARD.encode(encoder.finish().unwrap())
         )
     }
 
-    pub(crate) fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
+    /// Import a string from `export_string`, a link containing one, or query parameters.
+    /// Strings exported by any of `compat::VERSIONS` are converted by `compat::import`; any other
+    /// version apart from the current one is rejected.
+    pub fn import_string(string: &str) -> Result<Self, InvalidSettingsImportError> {
+        Self::import_unchecked(string)?.checked()
+    }
+
+    /// Reject settings with values that would break rendering, which a corrupted or hand-edited
+    /// string could contain
+    fn checked(self) -> Result<Self, InvalidSettingsImportError> {
+        let finite = |values: &[f64]| values.iter().all(|x| x.is_finite());
+        if finite(&[self.zoom])
+            && self.zoom > 0.0
+            && finite(&self.centre)
+            && finite(&self.initial_value)
+            && finite(&self.julia_c)
+            && self.iterations >= 1
+            && self.escape_threshold.is_finite()
+            && self.escape_threshold > 0.0
+            && self.palette.len().is_multiple_of(4)
+            && self.palette.len() <= MAX_PALETTE_WIDTH * 4
+            // A NaN render scale passes through clamping and gives a NaN render size
+            && self.render_scale > 0.0
+            && self.render_scale <= 1.0
+            && (0.0..=1.0).contains(&self.smoothing_strength)
+            && self.smoothing_power.is_finite()
+            && self.smoothing_power > 1.0
+            && self.scroll_sensitivity.is_finite()
+            && self.scroll_sensitivity > 0.0
+            && MULTIBROT_POWERS.contains(&self.multibrot_power)
+            && self.auto_iterations_rate.is_finite()
+            && self.auto_iterations_rate >= 0.0
+        {
+            Ok(self)
+        } else {
+            Err(InvalidSettingsImportError::OutOfRange)
+        }
+    }
+
+    fn import_unchecked(string: &str) -> Result<Self, InvalidSettingsImportError> {
         let string = match url::Url::parse(string) {
             Ok(url) => url.query().unwrap_or_default().to_string(),
             Err(_) => string.to_string(),
@@ -94,42 +714,103 @@ impl UserSettings {
             return Err(InvalidSettingsImportError::InvalidFormat);
         }
 
+        // Exported strings always contain a ';', but base64 can contain '=' as padding
+        if !string.contains(';') && string.contains('=') {
+            return Self::import_query_params(&string);
+        }
+
         let mut iterator = string.split(';');
 
         let major_minor_version = iterator
             .next()
             .ok_or(InvalidSettingsImportError::InvalidFormat)?;
 
-        let base64 = iterator
+        let mut base64 = iterator
             .next()
             .ok_or(InvalidSettingsImportError::InvalidFormat)?;
+        let compressed = base64 == COMPRESSED_MARKER;
+        if compressed {
+            base64 = iterator
+                .next()
+                .ok_or(InvalidSettingsImportError::InvalidFormat)?;
+        }
 
         let this_ver = get_major_minor_version();
         match major_minor_version {
             s if s == &this_ver => {
-                let bytes = general_purpose::STANDARD
+                let mut bytes = general_purpose::STANDARD
                     .decode(base64)
                     .map_err(|_| InvalidSettingsImportError::InvalidBase64)?;
+                if compressed {
+                    // Fall back to treating the data as uncompressed if it doesn't inflate
+                    let mut decompressed = Vec::new();
+                    if DeflateDecoder::new(bytes.as_slice())
+                        .read_to_end(&mut decompressed)
+                        .is_ok()
+                    {
+                        bytes = decompressed;
+                    }
+                }
                 let result = bincode::deserialize::<'_, Self>(bytes.as_slice())
                     .map_err(|_| InvalidSettingsImportError::DeserialisationFailed)?;
                 Ok(result)
             }
-            "2.0" => Ok(compat::v2_0::UserSettings::import_string(base64)?.into()),
-            "0.5" => Ok(compat::v0_5::UserSettings::import_string(base64)?.into()),
-            "0.3" => Ok(compat::v0_3::UserSettings::import_string(base64)?.into()),
-            "0.4" => Ok(compat::v0_4::UserSettings::import_string(base64)?.into()),
-            _ => Err(InvalidSettingsImportError::VersionMismatch),
+            version => compat::import(version, base64)
+                .unwrap_or(Err(InvalidSettingsImportError::VersionMismatch)),
+        }
+    }
+
+    /// Read settings embedded in a PNG screenshot's text metadata
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn import_png(data: &[u8]) -> Result<Self, InvalidSettingsImportError> {
+        let reader = png::Decoder::new(data)
+            .read_info()
+            .map_err(|_| InvalidSettingsImportError::InvalidFormat)?;
+        let text = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == PNG_SETTINGS_KEYWORD)
+            .ok_or(InvalidSettingsImportError::NoImageMetadata)?;
+        Self::import_string(&text.text)
+    }
+
+    /// Import human-readable query parameters, e.g. `re=-0.7436&im=0.1318&zoom=1e6&iter=500`.
+    /// Anything not specified is left at its default value.
+    pub(crate) fn import_query_params(query: &str) -> Result<Self, InvalidSettingsImportError> {
+        let mut settings = Self::default();
+
+        fn invalid<E>(_: E) -> InvalidSettingsImportError {
+            InvalidSettingsImportError::InvalidQueryParameter
+        }
+
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "re" => settings.centre[0] = value.parse().map_err(invalid)?,
+                "im" => settings.centre[1] = value.parse().map_err(invalid)?,
+                "zoom" => settings.zoom = value.parse().map_err(invalid)?,
+                "iter" => settings.iterations = value.parse().map_err(invalid)?,
+                _ => return Err(InvalidSettingsImportError::InvalidQueryParameter),
+            }
+        }
+
+        if !settings.zoom.is_finite() || settings.zoom <= 0.0 || settings.iterations < 1 {
+            return Err(InvalidSettingsImportError::InvalidQueryParameter);
         }
+
+        Ok(settings)
     }
 }
 
 impl Default for CustomShaderData {
     fn default() -> Self {
         Self {
-            equation: "csquare(z) + c".to_string(),
-            colour: "hsv_rgb(vec3(log(n + 1.0) / log(f32(uniforms.iterations) + 1.0), 0.8, 0.8))"
-                .to_string(),
+            equation: STANDARD_EQUATION.to_string(),
+            colour: DEFAULT_COLOUR.to_string(),
             additional: String::new(),
+            derivative: STANDARD_DERIVATIVE.to_string(),
+            interior_colour: DEFAULT_INTERIOR_COLOUR.to_string(),
+            escape_condition: DEFAULT_ESCAPE_CONDITION.to_string(),
         }
     }
 }
@@ -143,10 +824,43 @@ impl Default for UserSettings {
             julia_set: false,
             smoothen: false,
             internal_black: true,
+            interior_colour: [0, 0, 0],
             initial_value: [0.0, 0.0],
+            julia_c: [0.0, 0.0],
             escape_threshold: 2.0,
             initial_c: false,
             shader_data: Default::default(),
+            high_precision: false,
+            distance_estimation: false,
+            samples: 1,
+            multibrot_power: 2.0,
+            edge_antialiasing: false,
+            mode: FractalMode::EscapeTime,
+            roots: vec![
+                [1.0, 0.0],
+                [-0.5, 0.75f64.sqrt()],
+                [-0.5, -(0.75f64.sqrt())],
+            ],
+            orbit_trap: OrbitTrap::default(),
+            use_palette: false,
+            palette: Vec::new(),
+            smoothing_strength: 1.0,
+            smoothing_power: 2.0,
+            interior_check: true,
+            period_colouring: false,
+            render_scale: 1.0,
+            frame_rate_limit: FrameRateLimit::Uncapped,
+            scroll_sensitivity: 1.0,
+            invert_scroll: false,
+            pan_inertia: true,
+            show_grid: false,
+            invert_colours: InvertColours::Off,
+            framing: Framing::ShorterSide,
+            keep_scale_on_resize: true,
+            julia_morph: 0.0,
+            auto_iterations: false,
+            auto_iterations_rate: 50.0,
+            auto_iterations_limit: 10000,
         }
     }
 }
@@ -0,0 +1,452 @@
+use super::*;
+
+const EQUATION: &str = "csquare(z) + c";
+const COLOUR: &str = "hsv_rgb(vec3(n / 100.0, 0.8, 0.8))";
+const ADDITIONAL: &str = "fn f(z: vec2<f32>) -> vec2<f32> { return z; }";
+
+// Strings exported by older versions, with zoom 2, centre -0.5 + 0.25i, 250 iterations, initial
+// value 0.125 - 0.25i and escape threshold 4
+const V0_3_EXPORT: &str = "0.3;AAAAQAAAAL8AAIA++gAAAA4AAAAAAAAAY3NxdWFyZSh6KSArIGMOAAAAAAAAAGNzcXVhcmUoeikgKyBjAQEAAAA+AACAvgAAgEA=";
+const V0_4_EXPORT: &str = "0.4;AAAAQAAAAL8AAIA++gAAAA4AAAAAAAAAY3NxdWFyZSh6KSArIGMOAAAAAAAAAGNzcXVhcmUoeikgKyBjIgAAAAAAAABoc3ZfcmdiKHZlYzMobiAvIDEwMC4wLCAwLjgsIDAuOCkpIgAAAAAAAABoc3ZfcmdiKHZlYzMobiAvIDEwMC4wLCAwLjgsIDAuOCkpAQABAAAAAD4AAIC+AACAQA==";
+const V0_5_EXPORT: &str = "0.5;AAAAQAAAAL8AAIA++gAAAA4AAAAAAAAAY3NxdWFyZSh6KSArIGMOAAAAAAAAAGNzcXVhcmUoeikgKyBjIgAAAAAAAABoc3ZfcmdiKHZlYzMobiAvIDEwMC4wLCAwLjgsIDAuOCkpIgAAAAAAAABoc3ZfcmdiKHZlYzMobiAvIDEwMC4wLCAwLjgsIDAuOCkpAQABAAAAAD4AAIC+AACAQAE=";
+const V2_0_EXPORT: &str = "2.0;AAAAQAAAAL8AAIA++gAAAA4AAAAAAAAAY3NxdWFyZSh6KSArIGMiAAAAAAAAAGhzdl9yZ2IodmVjMyhuIC8gMTAwLjAsIDAuOCwgMC44KSkAAQAAAAA+AACAvgAAgEAB";
+const V2_1_EXPORT: &str = "2.1;AAAAQAAAAL8AAIA++gAAAAABAAAAAD4AAIC+AACAQAEOAAAAAAAAAGNzcXVhcmUoeikgKyBjIgAAAAAAAABoc3ZfcmdiKHZlYzMobiAvIDEwMC4wLCAwLjgsIDAuOCkpLQAAAAAAAABmbiBmKHo6IHZlYzI8ZjMyPikgLT4gdmVjMjxmMzI+IHsgcmV0dXJuIHo7IH0=";
+
+/// The view all the old exports above contain, with everything they don't store left as default
+fn old_export_view(shader_data: CustomShaderData) -> UserSettings {
+    UserSettings {
+        zoom: 2.0,
+        centre: [-0.5, 0.25],
+        iterations: 250,
+        initial_value: [0.125, -0.25],
+        julia_c: [0.125, -0.25],
+        escape_threshold: 4.0,
+        shader_data,
+        ..Default::default()
+    }
+}
+
+fn shader_data(colour: &str, additional: &str) -> CustomShaderData {
+    CustomShaderData {
+        equation: EQUATION.to_string(),
+        colour: colour.to_string(),
+        additional: additional.to_string(),
+        ..Default::default()
+    }
+}
+
+fn import(string: &str) -> UserSettings {
+    match UserSettings::import_string(string) {
+        Ok(settings) => settings,
+        Err(e) => panic!("failed to import {string}: {e}"),
+    }
+}
+
+#[test]
+fn export_round_trips() {
+    let settings = UserSettings {
+        zoom: 12345.678,
+        centre: [-0.743643887037151, 0.131825904205330],
+        iterations: 5000,
+        julia_set: true,
+        smoothen: true,
+        julia_c: [-0.8, 0.156],
+        shader_data: shader_data(COLOUR, ADDITIONAL),
+        mode: FractalMode::Newton,
+        roots: vec![[1.0, 0.0], [-1.0, 0.0]],
+        orbit_trap: OrbitTrap {
+            shape: OrbitTrapShape::Cross,
+            centre: [0.5, -0.5],
+            angle: 0.25,
+        },
+        frame_rate_limit: FrameRateLimit::Fps30,
+        invert_colours: InvertColours::Iterations,
+        ..Default::default()
+    };
+    assert!(import(&settings.export_string()) == settings);
+}
+
+#[test]
+fn default_export_round_trips() {
+    let settings = UserSettings::default();
+    assert!(import(&settings.export_string()) == settings);
+}
+
+#[test]
+fn imports_link() {
+    let settings = UserSettings {
+        zoom: 100.0,
+        ..Default::default()
+    };
+    let link = format!(
+        "https://arthomnix.dev/fractal/?{}",
+        settings.export_string()
+    );
+    assert!(import(&link) == settings);
+}
+
+#[test]
+fn imports_uncompressed_current_version() {
+    let encoded =
+        general_purpose::STANDARD.encode(bincode::serialize(&UserSettings::default()).unwrap());
+    let string = format!("{};{encoded}", get_major_minor_version());
+    assert!(import(&string) == UserSettings::default());
+}
+
+#[test]
+fn imports_query_params() {
+    let settings = import("re=-0.75&im=0.1&zoom=1000&iter=500");
+    assert_eq!(settings.centre, [-0.75, 0.1]);
+    assert_eq!(settings.zoom, 1000.0);
+    assert_eq!(settings.iterations, 500);
+}
+
+#[test]
+fn imports_v0_3() {
+    let expected = UserSettings {
+        julia_set: true,
+        ..old_export_view(CustomShaderData {
+            equation: EQUATION.to_string(),
+            ..Default::default()
+        })
+    };
+    assert!(import(V0_3_EXPORT) == expected);
+}
+
+#[test]
+fn imports_v0_4() {
+    let expected = UserSettings {
+        smoothen: true,
+        internal_black: false,
+        ..old_export_view(shader_data(COLOUR, ""))
+    };
+    assert!(import(V0_4_EXPORT) == expected);
+}
+
+#[test]
+fn imports_v0_5() {
+    let expected = UserSettings {
+        smoothen: true,
+        internal_black: false,
+        initial_c: true,
+        ..old_export_view(shader_data(COLOUR, ""))
+    };
+    assert!(import(V0_5_EXPORT) == expected);
+}
+
+#[test]
+fn imports_v2_0() {
+    let expected = UserSettings {
+        smoothen: true,
+        internal_black: false,
+        initial_c: true,
+        ..old_export_view(shader_data(COLOUR, ""))
+    };
+    assert!(import(V2_0_EXPORT) == expected);
+}
+
+#[test]
+fn imports_v2_1() {
+    let expected = UserSettings {
+        smoothen: true,
+        internal_black: false,
+        initial_c: true,
+        ..old_export_view(shader_data(COLOUR, ADDITIONAL))
+    };
+    assert!(import(V2_1_EXPORT) == expected);
+}
+
+#[test]
+fn every_compat_version_imports() {
+    let exports = [
+        V0_3_EXPORT,
+        V0_4_EXPORT,
+        V0_5_EXPORT,
+        V2_0_EXPORT,
+        V2_1_EXPORT,
+    ];
+    for version in compat::VERSIONS {
+        let export = exports
+            .iter()
+            .find(|export| export.split(';').next() == Some(version))
+            .unwrap_or_else(|| panic!("no test export for version {version}"));
+        import(export);
+    }
+    assert_eq!(exports.len(), compat::VERSIONS.len());
+}
+
+#[test]
+fn rejects_unknown_version() {
+    assert!(matches!(
+        UserSettings::import_string("1.0;AAAA"),
+        Err(InvalidSettingsImportError::VersionMismatch)
+    ));
+}
+
+#[test]
+fn rejects_invalid_data() {
+    let string = format!("{};not base64!", get_major_minor_version());
+    assert!(matches!(
+        UserSettings::import_string(&string),
+        Err(InvalidSettingsImportError::InvalidBase64)
+    ));
+    assert!(matches!(
+        UserSettings::import_string("2.1;AAAA"),
+        Err(InvalidSettingsImportError::DeserialisationFailed)
+    ));
+}
+
+fn assert_shader_valid(shader_data: &CustomShaderData) {
+    crate::ShaderValidator::new()
+        .validate_shader(shader_data)
+        .unwrap_or_else(|e| panic!("{} is invalid: {e}", shader_data.colour));
+}
+
+#[test]
+fn colour_expression_presets_validate() {
+    for (_, colour) in COLOUR_EXPRESSION_PRESETS {
+        assert_shader_valid(&CustomShaderData {
+            colour: colour.to_string(),
+            ..Default::default()
+        });
+    }
+    assert_shader_valid(&CustomShaderData {
+        colour: "hsv_rgb(vec3(0.5)) + hsl_rgb(vec3(0.5)) + oklab_rgb(vec3(0.5, 0.0, 0.0))"
+            .to_string(),
+        ..Default::default()
+    });
+}
+
+#[test]
+fn equation_presets_validate() {
+    for preset in EQUATION_PRESETS {
+        if let Err(e) = crate::validate_expression(preset.equation, preset.colour, "") {
+            panic!("{} failed to validate: {e}", preset.name);
+        }
+    }
+    assert!(crate::validate_expression(EQUATION, COLOUR, ADDITIONAL).is_ok());
+    assert!(crate::validate_expression("f(z) + c", COLOUR, ADDITIONAL).is_ok());
+}
+
+#[test]
+fn escape_conditions_validate() {
+    for escape_condition in [
+        DEFAULT_ESCAPE_CONDITION,
+        "dot(z, z) > threshold * threshold",
+        "trap < 0.01 || length(z) > threshold",
+    ] {
+        assert_shader_valid(&CustomShaderData {
+            escape_condition: escape_condition.to_string(),
+            ..Default::default()
+        });
+    }
+    assert!(CustomShaderData::default().has_default_escape_condition());
+}
+
+#[test]
+fn validate_expression_rejects_invalid_input() {
+    assert!(matches!(
+        crate::validate_expression("csquare(z) +", COLOUR, ""),
+        Err(crate::ShaderError::Parse(_))
+    ));
+    // Has the wrong type, which naga may report while parsing or validating
+    assert!(crate::validate_expression("length(z)", COLOUR, "").is_err());
+    // The additional code has to be valid too, even if nothing uses it
+    assert!(matches!(
+        crate::validate_expression(EQUATION, COLOUR, "fn f("),
+        Err(crate::ShaderError::Parse(_))
+    ));
+}
+
+#[test]
+fn rejects_out_of_range_values() {
+    let settings = UserSettings {
+        zoom: f64::INFINITY,
+        ..Default::default()
+    };
+    assert!(matches!(
+        UserSettings::import_string(&settings.export_string()),
+        Err(InvalidSettingsImportError::OutOfRange)
+    ));
+
+    let settings = UserSettings {
+        centre: [f64::NAN, 0.0],
+        ..Default::default()
+    };
+    assert!(matches!(
+        UserSettings::import_string(&settings.export_string()),
+        Err(InvalidSettingsImportError::OutOfRange)
+    ));
+
+    assert!(matches!(
+        UserSettings::import_string("re=inf&im=0"),
+        Err(InvalidSettingsImportError::OutOfRange)
+    ));
+
+    // Palettes must be whole RGBA pixels
+    let settings = UserSettings {
+        palette: vec![255; 6],
+        ..Default::default()
+    };
+    assert!(matches!(
+        UserSettings::import_string(&settings.export_string()),
+        Err(InvalidSettingsImportError::OutOfRange)
+    ));
+}
+
+#[test]
+fn colour_preset_round_trips() {
+    let settings = UserSettings {
+        shader_data: CustomShaderData {
+            colour: COLOUR.to_string(),
+            interior_colour: "vec3(fract(length(z)))".to_string(),
+            ..Default::default()
+        },
+        internal_black: false,
+        interior_colour: [255, 128, 0],
+        smoothen: true,
+        smoothing_strength: 0.5,
+        smoothing_power: 3.0,
+        invert_colours: InvertColours::Iterations,
+        use_palette: true,
+        palette: vec![0, 0, 0, 255, 255, 255, 255, 255],
+        ..Default::default()
+    };
+    let preset = ColourPreset::from_settings("test".to_string(), &settings);
+    let imported = ColourPreset::import_string(&preset.export_string()).unwrap();
+    assert!(imported == preset);
+
+    let mut applied = UserSettings::default();
+    imported.apply(&mut applied);
+    assert_eq!(applied.shader_data.colour, COLOUR);
+    assert!(!applied.internal_black);
+    assert_eq!(applied.interior_colour, [255, 128, 0]);
+    assert_eq!(
+        applied.shader_data.interior_colour,
+        "vec3(fract(length(z)))"
+    );
+    assert!(applied.smoothen);
+    assert_eq!(applied.smoothing_strength, 0.5);
+    assert_eq!(applied.smoothing_power, 3.0);
+    assert!(applied.invert_colours == InvertColours::Iterations);
+    assert!(applied.use_palette);
+    assert_eq!(applied.palette, settings.palette);
+}
+
+fn assert_out_of_range(settings: UserSettings) {
+    assert!(matches!(
+        UserSettings::import_string(&settings.export_string()),
+        Err(InvalidSettingsImportError::OutOfRange)
+    ));
+}
+
+#[test]
+fn rejects_out_of_range_render_scale() {
+    assert_out_of_range(UserSettings {
+        render_scale: f32::NAN,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        render_scale: 0.0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rejects_out_of_range_smoothing() {
+    assert_out_of_range(UserSettings {
+        smoothing_strength: 1.5,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        smoothing_power: f32::INFINITY,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        smoothing_power: 1.0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rejects_out_of_range_scroll_sensitivity() {
+    assert_out_of_range(UserSettings {
+        scroll_sensitivity: f32::NAN,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        scroll_sensitivity: -1.0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rejects_out_of_range_multibrot_power() {
+    assert_out_of_range(UserSettings {
+        multibrot_power: f32::NAN,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        multibrot_power: 100.0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rejects_out_of_range_auto_iterations_rate() {
+    assert_out_of_range(UserSettings {
+        auto_iterations_rate: f64::INFINITY,
+        ..Default::default()
+    });
+    assert_out_of_range(UserSettings {
+        auto_iterations_rate: -1.0,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn file_name_describes_view() {
+    let settings = UserSettings {
+        zoom: 1.2e5,
+        centre: [-0.75, 0.1],
+        ..Default::default()
+    };
+    assert_eq!(settings.file_name("png"), "fractal_z1.2e5_c-0.75+0.1i.png");
+
+    let settings = UserSettings {
+        centre: [0.25, -0.5],
+        ..Default::default()
+    };
+    assert_eq!(settings.file_name("png"), "fractal_z1.0e0_c0.25-0.5i.png");
+}
+
+#[test]
+fn multibrot_equations() {
+    assert_eq!(multibrot_equation(2.0), STANDARD_EQUATION);
+    assert_eq!(multibrot_equation(5.0), "cpowi(z, 5) + c");
+    assert_eq!(multibrot_equation(2.504), "cpow(z, 2.5) + c");
+    for power in [2.0, 3.0, 5.0, 2.5] {
+        let shader_data = CustomShaderData {
+            equation: multibrot_equation(power),
+            ..Default::default()
+        };
+        assert_eq!(shader_data.equation_power(), Some(power));
+        assert!(shader_data.is_holomorphic_power());
+        assert_shader_valid(&shader_data);
+    }
+}
+
+#[test]
+fn exact_derivative_only_for_holomorphic_powers() {
+    for equation in [
+        "csquare(abs(z)) + c",
+        "csquare(vec2<f32>(z.x, -z.y)) + c",
+        "csin(z) + c",
+    ] {
+        let shader_data = CustomShaderData {
+            equation: equation.to_string(),
+            ..Default::default()
+        };
+        assert!(!shader_data.is_holomorphic_power(), "{equation}");
+    }
+}
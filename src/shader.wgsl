@@ -5,15 +5,76 @@ struct Uniforms {
     iterations: i32,
     flags: u32,
     initial_value: vec2<f32>,
+    // Low parts of double-single values, only used in high precision mode
+    centre_lo: vec2<f32>,
+    initial_value_lo: vec2<f32>,
+    scale_lo: f32,
+    // Number of samples per pixel along each axis
+    samples: u32,
+    root_count: u32,
+    // Roots to colour by in Newton mode, in the xy components
+    roots: array<vec4<f32>, 8>,
+    trap_centre: vec2<f32>,
+    trap_shape: u32,
+    trap_angle: f32,
+    // Power of z in the equation, for smooth colouring
+    power: f32,
+    // Seconds of colour cycling, for animated colour expressions
+    time: f32,
+    // sRGB colour for points that don't escape when INTERNAL_BLACK is set, in the rgb components
+    interior_colour: vec4<f32>,
+    // Value of c for the Julia set being morphed towards
+    morph_c: vec2<f32>,
+    // How far the Mandelbrot set is morphed towards the Julia set, from 0 to 1
+    morph: f32,
+    // How far n is blended from the integer iteration count to the smoothed one when SMOOTHEN is
+    // set, from 0 to 1
+    smoothing: f32,
 }
 
 const JULIA_SET = 1u;
 const SMOOTHEN = 2u;
 const INTERNAL_BLACK = 4u;
 const INITIAL_C = 8u;
+const ITERATION_PROBE = 16u;
+const HIGH_PRECISION = 32u;
+const DISTANCE_ESTIMATION = 64u;
+const NEWTON = 128u;
+const INTERIOR_CHECK = 256u;
+const SRGB_TARGET = 512u;
+const GREYSCALE_ITERATIONS = 1024u;
+const ITERATION_STATS = 2048u;
+const INVERT_ITERATIONS = 4096u;
+const INVERT_RGB = 8192u;
+const PERIOD_COLOURING = 16384u;
+const PALETTE = 32768u;
+const EDGE_ANTIALIASING = 65536u;
+const EXACT_DERIVATIVE = 131072u;
+
+// How close z has to come to an earlier value for the orbit to be treated as periodic
+const PERIOD_EPSILON = 1e-7;
+
+// Iterations run after the limit before looking for a cycle, to let the orbit settle onto it
+const PERIOD_SETTLE_ITERATIONS = 256;
+// Longest cycle looked for when colouring the inside of the set by period
+const MAX_PERIOD = 64;
+// Relative distance within which z counts as having returned to the start of its cycle
+const PERIOD_TOLERANCE = 1e-4;
+
+const TRAP_POINT = 1u;
+const TRAP_LINE = 2u;
+const TRAP_CROSS = 3u;
+
+// Relative step size used to estimate the derivative of the user's equation, when it isn't one
+// whose derivative is known exactly
+const DE_STEP = 0.001;
 
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
 
+// A row of colours loaded from an image, used in place of the colour expression when PALETTE is set
+@group(1) @binding(0) var palette_texture: texture_2d<f32>;
+@group(1) @binding(1) var palette_sampler: sampler;
+
 @vertex
 fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
     var vertex_positions: array<vec4<f32>, 6> = array<vec4<f32>, 6>(
@@ -33,6 +94,24 @@ fn cpow(z: vec2<f32>, p: f32) -> vec2<f32> {
     return vec2<f32>(pow(r, p) * cos(p * arg), pow(r, p) * sin(p * arg));
 }
 
+// z to a whole power by repeated squaring, which is more precise than cpow and has no branch cut
+fn cpowi(z: vec2<f32>, n: i32) -> vec2<f32> {
+    var result = vec2(1.0, 0.0);
+    var base = z;
+    var e = n;
+    if (e < 0) {
+        base = cdiv(vec2(1.0, 0.0), z);
+        e = -e;
+    }
+    for (; e > 0; e >>= 1u) {
+        if ((e & 1) != 0) {
+            result = cmul(result, base);
+        }
+        base = csquare(base);
+    }
+    return result;
+}
+
 fn ccpow(z: vec2<f32>, w: vec2<f32>) -> vec2<f32> {
     let r: f32 = length(z);
     var len: f32 = pow(r, w.x);
@@ -57,6 +136,95 @@ fn csquare(z: vec2<f32>) -> vec2<f32> {
     return cmul(z, z);
 }
 
+fn cexp(z: vec2<f32>) -> vec2<f32> {
+    return exp(z.x) * vec2<f32>(cos(z.y), sin(z.y));
+}
+
+fn clog(z: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(log(length(z)), atan2(z.y, z.x));
+}
+
+fn csqrt(z: vec2<f32>) -> vec2<f32> {
+    let r = length(z);
+    return vec2<f32>(sqrt((r + z.x) / 2.0), select(1.0, -1.0, z.y < 0.0) * sqrt((r - z.x) / 2.0));
+}
+
+fn csin(z: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(sin(z.x) * cosh(z.y), cos(z.x) * sinh(z.y));
+}
+
+fn ccos(z: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(cos(z.x) * cosh(z.y), -sin(z.x) * sinh(z.y));
+}
+
+// Double-single arithmetic: a vec2 represents the unevaluated sum of its components, giving
+// roughly twice the precision of an f32. Complex double-single numbers are stored in a vec4 as
+// (re.hi, re.lo, im.hi, im.lo).
+
+fn ds_quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    return vec2<f32>(s, b - (s - a));
+}
+
+fn ds_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = a.x + b.x;
+    let v = s - a.x;
+    let e = (a.x - (s - v)) + (b.x - v);
+    return ds_quick_two_sum(s, e + a.y + b.y);
+}
+
+fn ds_sub(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return ds_add(a, -b);
+}
+
+fn ds_split(a: f32) -> vec2<f32> {
+    let t = 4097.0 * a;
+    let hi = t - (t - a);
+    return vec2<f32>(hi, a - hi);
+}
+
+fn ds_two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    let sa = ds_split(a);
+    let sb = ds_split(b);
+    let e = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    return vec2<f32>(p, e);
+}
+
+fn ds_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = ds_two_prod(a.x, b.x);
+    return ds_quick_two_sum(p.x, p.y + (a.x * b.y + a.y * b.x));
+}
+
+fn ds_div(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let q1 = a.x / b.x;
+    let r = ds_sub(a, ds_mul(vec2<f32>(q1, 0.0), b));
+    return ds_quick_two_sum(q1, r.x / b.x);
+}
+
+fn ds_cadd(w: vec4<f32>, z: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(ds_add(w.xy, z.xy), ds_add(w.zw, z.zw));
+}
+
+fn ds_cmul(w: vec4<f32>, z: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        ds_sub(ds_mul(w.xy, z.xy), ds_mul(w.zw, z.zw)),
+        ds_add(ds_mul(w.xy, z.zw), ds_mul(w.zw, z.xy)),
+    );
+}
+
+fn ds_csquare(z: vec4<f32>) -> vec4<f32> {
+    return ds_cmul(z, z);
+}
+
+fn ds_cdiv(w: vec4<f32>, z: vec4<f32>) -> vec4<f32> {
+    let denom = ds_add(ds_mul(z.xy, z.xy), ds_mul(z.zw, z.zw));
+    return vec4<f32>(
+        ds_div(ds_add(ds_mul(w.xy, z.xy), ds_mul(w.zw, z.zw)), denom),
+        ds_div(ds_sub(ds_mul(w.zw, z.xy), ds_mul(w.xy, z.zw)), denom),
+    );
+}
+
 fn hsv_rgb(hsv: vec3<f32>) -> vec3<f32> {
     if (hsv.y == 0.0) {
         return vec3<f32>(hsv.z, hsv.z, hsv.z);
@@ -92,6 +260,34 @@ fn hsv_rgb(hsv: vec3<f32>) -> vec3<f32> {
     }
 }
 
+fn hsl_rgb(hsl: vec3<f32>) -> vec3<f32> {
+    let chroma = (1.0 - abs(2.0 * hsl.z - 1.0)) * hsl.y;
+    let hue = clamp(
+        abs(fract(hsl.x + vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0,
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+    return hsl.z + chroma * (hue - 0.5);
+}
+
+// Convert an Oklab colour (lightness, green-red, blue-yellow) to sRGB, clamping colours outside the
+// sRGB gamut (https://bottosson.github.io/posts/oklab/)
+fn oklab_rgb(lab: vec3<f32>) -> vec3<f32> {
+    // pow is undefined for negative bases, so cube by multiplying
+    let lms = vec3<f32>(
+        lab.x + 0.3963377774 * lab.y + 0.2158037573 * lab.z,
+        lab.x - 0.1055613458 * lab.y - 0.0638541728 * lab.z,
+        lab.x - 0.0894841775 * lab.y - 1.2914855480 * lab.z,
+    );
+    let cubed = lms * lms * lms;
+    let linear = vec3<f32>(
+        4.0767416621 * cubed.x - 3.3077115913 * cubed.y + 0.2309699292 * cubed.z,
+        -1.2684380046 * cubed.x + 2.6097574011 * cubed.y - 0.3413193965 * cubed.z,
+        -0.0041960863 * cubed.x - 0.7034186147 * cubed.y + 1.7076147010 * cubed.z,
+    );
+    return linear_to_srgb(clamp(linear, vec3<f32>(0.0), vec3<f32>(1.0)));
+}
+
 fn rgb(rgb: u32) -> vec3<f32> {
     return vec3(
         f32((rgb & 0xFF0000) >> 16),
@@ -100,61 +296,507 @@ fn rgb(rgb: u32) -> vec3<f32> {
     ) / 255.0;
 }
 
-fn get_fragment_colour(c: vec2<f32>) -> vec4<f32> {
+// Map an iteration count logarithmically to 0..1, spreading out the colours of low counts
+fn log_scale(n: f32) -> f32 {
+    return log(n + 1.0) / log(f32(uniforms.iterations) + 1.0);
+}
+
+// Look up the colour t of the way along the palette image
+fn sample_palette(t: f32) -> vec3<f32> {
+    return textureSampleLevel(palette_texture, palette_sampler, vec2(clamp(t, 0.0, 1.0), 0.5), 0.0).rgb;
+}
+
+// Blend from colour a at t = 0 to colour b at t = 1
+fn linear_palette(t: f32, a: vec3<f32>, b: vec3<f32>) -> vec3<f32> {
+    return mix(a, b, clamp(t, 0.0, 1.0));
+}
+
+// Inigo Quilez's cosine palette: a is the average colour, b the amplitude, c the frequency and d
+// the phase of each channel (https://iquilezles.org/articles/palettes/)
+fn cosine_palette(t: f32, a: vec3<f32>, b: vec3<f32>, c: vec3<f32>, d: vec3<f32>) -> vec3<f32> {
+    return a + b * cos(6.28318 * (c * t + d));
+}
+
+// Distance from z to the orbit trap shape
+fn trap_distance(z: vec2<f32>) -> f32 {
+    let p = z - uniforms.trap_centre;
+    let direction = vec2<f32>(cos(uniforms.trap_angle), sin(uniforms.trap_angle));
+    let line = abs(dot(p, vec2<f32>(-direction.y, direction.x)));
+    if (uniforms.trap_shape == TRAP_POINT) {
+        return length(p);
+    } else if (uniforms.trap_shape == TRAP_LINE) {
+        return line;
+    } else if (uniforms.trap_shape == TRAP_CROSS) {
+        return min(line, abs(dot(p, direction)));
+    }
+    return 0.0;
+}
+
+// Pack an iteration count into a colour so it can be read back from an 8-bit render target
+fn encode_iterations(i: i32) -> vec4<f32> {
+    let u = u32(i);
+    return vec4<f32>(
+        f32(u & 0xFFu),
+        f32((u >> 8u) & 0xFFu),
+        f32((u >> 16u) & 0xFFu),
+        select(0.0, 255.0, i < uniforms.iterations),
+    ) / 255.0;
+}
+
+// Whether c is in the main cardioid or period 2 bulb of the Mandelbrot set
+fn in_main_bulbs(c: vec2<f32>) -> bool {
+    let x = c.x - 0.25;
+    let q = x * x + c.y * c.y;
+    let bulb = (c.x + 1.0) * (c.x + 1.0) + c.y * c.y;
+    return q * (q + x) <= 0.25 * c.y * c.y || bulb <= 0.0625;
+}
+
+// Whether the orbit has escaped, which stops iterating. threshold is the escape threshold, and trap
+// the closest the orbit has come to the orbit trap before reaching z.
+fn has_escaped(z: vec2<f32>, c: vec2<f32>, trap: f32, threshold: f32) -> bool {
+    return REPLACE_ESCAPE_CONDITION; // gets replaced by user-defined expression
+}
+
+// Colour for points inside the set when it is filled. z is the value z had when iteration stopped,
+// trap the closest the orbit came to the orbit trap, and period the length of the cycle the orbit
+// settles into, or 0 if it wasn't looked for or found.
+fn solid_interior(z: vec2<f32>, c: vec2<f32>, trap: f32, period: i32) -> vec4<f32> {
+    let colour = REPLACE_INTERIOR_COLOR; // gets replaced by user-defined expression
+    return vec4<f32>(colour, 1.0);
+}
+
+// Colour for points found to be inside the set without iterating to the limit. The interior check
+// is only enabled when the inside of the set is filled with a solid colour, so the orbit doesn't
+// matter. `executed` is how many iterations were run before the point was found to be inside the set
+fn interior_colour(executed: i32) -> vec4<f32> {
+    if ((uniforms.flags & ITERATION_STATS) != 0u) {
+        return encode_iterations(executed);
+    }
+    if ((uniforms.flags & ITERATION_PROBE) != 0u) {
+        return encode_iterations(uniforms.iterations);
+    }
+    return solid_interior(vec2<f32>(0.0), vec2<f32>(0.0), 0.0, 0);
+}
+
+// Derivative of z^p, where p is the equation's power, for tracking the derivative of the orbit
+// exactly. Whole powers use cpowi for the same reasons as multibrot equations.
+fn power_derivative(z: vec2<f32>) -> vec2<f32> {
+    let p = uniforms.power;
+    if (p == 2.0) {
+        return 2.0 * z;
+    }
+    if (fract(p) == 0.0) {
+        return p * cpowi(z, i32(p) - 1);
+    }
+    return p * cpow(z, p - 1.0);
+}
+
+// Length of the cycle that the orbit through z settles into, or 0 if none is found. Only used for
+// the standard equation, z^2 + c.
+fn find_period(start: vec2<f32>, c: vec2<f32>) -> i32 {
+    var z = start;
+    for (var i = 0; i < PERIOD_SETTLE_ITERATIONS; i++) {
+        z = csquare(z) + c;
+    }
+    let cycle_start = z;
+    let tolerance = PERIOD_TOLERANCE * max(length(cycle_start), 1.0);
+    for (var period = 1; period <= MAX_PERIOD; period++) {
+        z = csquare(z) + c;
+        if (length(z - cycle_start) < tolerance) {
+            return period;
+        }
+    }
+    return 0;
+}
+
+// Interior colour by the period of an orbit's cycle, which is the same across each bulb, for the
+// default interior colour expression. Period 0 gives the colour picked in the UI.
+fn period_colour(period: i32) -> vec3<f32> {
+    if (period == 0) {
+        return uniforms.interior_colour.rgb;
+    }
+    // Stepping by the golden ratio keeps neighbouring periods' hues well apart
+    return hsv_rgb(vec3<f32>(fract(f32(period) * 0.618034), 0.6, 0.85));
+}
+
+// How an orbit from `iterate` ended. Escaped also covers reaching the iteration limit when the
+// inside of the set isn't filled, as those points are coloured the same way.
+const ORBIT_ESCAPED = 0u;
+// Reached the iteration limit, with the inside of the set filled
+const ORBIT_INSIDE = 1u;
+// Found to be inside the set by the interior check, without iterating to the limit
+const ORBIT_SKIPPED = 2u;
+
+// Everything about an orbit needed to colour its pixel
+struct Orbit {
+    state: u32,
+    // Iterations run
+    i: i32,
+    // Value of z when iteration stopped
+    z: vec2<f32>,
+    c: vec2<f32>,
+    // Derivative of z with respect to c (or the starting z for Julia sets), for distance estimation
+    dz: vec2<f32>,
+    // Closest distance the orbit came to the orbit trap
+    trap: f32,
+}
+
+fn iterate(c: vec2<f32>) -> Orbit {
     var i: i32 = 0;
     var z: vec2<f32>;
+    var dz: vec2<f32>;
+    var trap = 3.4e38;
 
     if ((uniforms.flags & JULIA_SET) == 0u) {
+        if ((uniforms.flags & INTERIOR_CHECK) != 0u && in_main_bulbs(c)) {
+            return Orbit(ORBIT_SKIPPED, 0, vec2<f32>(0.0), vec2<f32>(0.0), vec2<f32>(0.0), 0.0);
+        }
+        // Morphing to the Julia set moves c from the pixel's position to a constant, and the
+        // starting value of z from the initial value to the pixel's position
+        let pixel = c;
+        var c = mix(pixel, uniforms.morph_c, uniforms.morph);
+        // Brent's cycle detection: compare against a saved z, saved again at doubling intervals
+        var period_z = vec2<f32>(3.4e38, 3.4e38);
+        var period_check_at = 1;
+
         if ((uniforms.flags & INITIAL_C) != 0u) {
             z = c;
+            dz = vec2<f32>(1.0, 0.0);
             i++;
         }
 
         for (
-            z += uniforms.initial_value;
-            length(z) < uniforms.escape_threshold;
+            z = mix(z + uniforms.initial_value, pixel, uniforms.morph);
+            !has_escaped(z, c, trap, uniforms.escape_threshold);
             z = REPLACE_FRACTAL_EQN // gets replaced by user-defined expression
         ) {
             i++;
+            trap = min(trap, trap_distance(z));
             if (i == uniforms.iterations) {
-                if ((uniforms.flags & INTERNAL_BLACK) != 0u) {
-                    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+                if ((uniforms.flags & INTERNAL_BLACK) != 0u && (uniforms.flags & ITERATION_PROBE) == 0u) {
+                    return Orbit(ORBIT_INSIDE, i, z, c, dz, trap);
                 } else {
                     break;
                 }
             }
+            if ((uniforms.flags & INTERIOR_CHECK) != 0u) {
+                if (length(z - period_z) < PERIOD_EPSILON) {
+                    return Orbit(ORBIT_SKIPPED, i, z, c, dz, trap);
+                }
+                if (i == period_check_at) {
+                    period_z = z;
+                    period_check_at *= 2;
+                }
+            }
+            if ((uniforms.flags & EXACT_DERIVATIVE) != 0u) {
+                // dz/dc = p z^(p-1) * dz/dc + 1 for z^p + c
+                dz = cmul(power_derivative(z), dz) + vec2<f32>(1.0, 0.0);
+            } else if ((uniforms.flags & DISTANCE_ESTIMATION) != 0u) {
+                // dz/dc = f'(z) * dz/dc + df/dc, with both derivatives estimated by finite differences
+                let de_f = REPLACE_FRACTAL_EQN;
+                let de_h = DE_STEP * max(length(z), 1.0);
+                let dz_len = length(dz);
+                var de_df = vec2<f32>(0.0, 0.0);
+                if (dz_len > 0.0) {
+                    let z = z + dz / dz_len * de_h;
+                    de_df = (REPLACE_FRACTAL_EQN - de_f) / de_h * dz_len;
+                }
+                {
+                    let c = c + vec2<f32>(de_h, 0.0);
+                    de_df += (REPLACE_FRACTAL_EQN - de_f) / de_h;
+                }
+                dz = de_df;
+            }
         }
     } else {
         z = c;
+        dz = vec2<f32>(1.0, 0.0);
         var c: vec2<f32> = uniforms.initial_value;
         for (;
-            length(z) < uniforms.escape_threshold;
+            !has_escaped(z, c, trap, uniforms.escape_threshold);
             z = REPLACE_FRACTAL_EQN // gets replaced by user-defined expression
         ) {
             i++;
+            trap = min(trap, trap_distance(z));
             if (i == uniforms.iterations) {
-                if ((uniforms.flags & INTERNAL_BLACK) != 0u) {
-                    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+                if ((uniforms.flags & INTERNAL_BLACK) != 0u && (uniforms.flags & ITERATION_PROBE) == 0u) {
+                    return Orbit(ORBIT_INSIDE, i, z, c, dz, trap);
                 } else {
                     break;
                 }
             }
+            if ((uniforms.flags & EXACT_DERIVATIVE) != 0u) {
+                // dz/dz0 = p z^(p-1) * dz/dz0 for z^p + c
+                dz = cmul(power_derivative(z), dz);
+            } else if ((uniforms.flags & DISTANCE_ESTIMATION) != 0u) {
+                // dz/dz0 = f'(z) * dz/dz0, with the derivative estimated by finite differences
+                let de_f = REPLACE_FRACTAL_EQN;
+                let de_h = DE_STEP * max(length(z), 1.0);
+                let dz_len = length(dz);
+                if (dz_len > 0.0) {
+                    let z = z + dz / dz_len * de_h;
+                    dz = (REPLACE_FRACTAL_EQN - de_f) / de_h * dz_len;
+                }
+            }
+        }
+    }
+
+    return Orbit(ORBIT_ESCAPED, i, z, c, dz, trap);
+}
+
+fn orbit_colour(orbit: Orbit) -> vec4<f32> {
+    if (orbit.state == ORBIT_SKIPPED) {
+        return interior_colour(orbit.i);
+    }
+    if (orbit.state == ORBIT_INSIDE) {
+        var period = 0;
+        if ((uniforms.flags & PERIOD_COLOURING) != 0u) {
+            period = find_period(orbit.z, orbit.c);
+        }
+        return solid_interior(orbit.z, orbit.c, orbit.trap, period);
+    }
+    return get_escaped_colour(orbit.i, orbit.z, orbit.c, orbit.dz, orbit.trap);
+}
+
+fn get_fragment_colour(c: vec2<f32>) -> vec4<f32> {
+    return orbit_colour(iterate(c));
+}
+
+// Colour for a pixel with the edge of the filled inside of the set smoothed. Each of a 2x2 rotated
+// grid of points in the pixel is iterated, and the colours of one inside and one escaped orbit are
+// blended by how many escaped. This iterates as much as 2x2 supersampling, but works out at most two
+// colours rather than four, and the interior check still skips most points inside the set.
+fn edge_antialiased_colour(pos: vec2<f32>) -> vec4<f32> {
+    var offsets = array<vec2<f32>, 4>(
+        vec2<f32>(-0.125, -0.375),
+        vec2<f32>(0.375, -0.125),
+        vec2<f32>(0.125, 0.375),
+        vec2<f32>(-0.375, 0.125),
+    );
+    var escaped = 0;
+    var inside: Orbit;
+    var outside: Orbit;
+    for (var s = 0; s < 4; s++) {
+        let orbit = iterate((pos + offsets[s]) * uniforms.scale - uniforms.centre);
+        if (orbit.state == ORBIT_ESCAPED) {
+            escaped++;
+            outside = orbit;
+        } else {
+            inside = orbit;
+        }
+    }
+
+    if (escaped == 0) {
+        return orbit_colour(inside);
+    }
+    if (escaped == 4) {
+        return orbit_colour(outside);
+    }
+    return mix(orbit_colour(inside), orbit_colour(outside), f32(escaped) / 4.0);
+}
+
+// High precision version of get_fragment_colour, which only supports the standard z^2 + c
+fn get_fragment_colour_hp(c: vec4<f32>) -> vec4<f32> {
+    var i: i32 = 0;
+    var z: vec4<f32>;
+    var k: vec4<f32>;
+    var dz: vec2<f32>;
+    var dk: vec2<f32>;
+    var trap = 3.4e38;
+    let initial_value = vec4<f32>(
+        uniforms.initial_value.x,
+        uniforms.initial_value_lo.x,
+        uniforms.initial_value.y,
+        uniforms.initial_value_lo.y,
+    );
+
+    if ((uniforms.flags & JULIA_SET) == 0u) {
+        z = initial_value;
+        k = c;
+        dk = vec2<f32>(1.0, 0.0);
+        if ((uniforms.flags & INITIAL_C) != 0u) {
+            z = ds_cadd(z, c);
+            dz = vec2<f32>(1.0, 0.0);
+            i++;
+        }
+    } else {
+        z = c;
+        k = initial_value;
+        dz = vec2<f32>(1.0, 0.0);
+    }
+
+    for (; length(z.xz) < uniforms.escape_threshold; z = ds_cadd(ds_csquare(z), k)) {
+        i++;
+        trap = min(trap, trap_distance(z.xz));
+        if (i == uniforms.iterations) {
+            if ((uniforms.flags & INTERNAL_BLACK) != 0u && (uniforms.flags & ITERATION_PROBE) == 0u) {
+                return solid_interior(z.xz, c.xz, trap, 0);
+            } else {
+                break;
+            }
+        }
+        if ((uniforms.flags & DISTANCE_ESTIMATION) != 0u) {
+            // The derivative doesn't need the extra precision, so it is tracked in single precision
+            dz = 2.0 * cmul(z.xz, dz) + dk;
         }
     }
 
+    return get_escaped_colour(i, z.xz, c.xz, dz, trap);
+}
+
+fn get_escaped_colour(i: i32, final_z: vec2<f32>, c: vec2<f32>, dz: vec2<f32>, trap: f32) -> vec4<f32> {
+    if ((uniforms.flags & ITERATION_PROBE) != 0u) {
+        return encode_iterations(i);
+    }
+
+    var z = final_z;
     var n = f32(i);
 
-    if ((uniforms.flags & SMOOTHEN) != 0u && i > 0) {
+    // Estimated distance to the edge of the set in pixels, or 0 inside the set
+    var de = 0.0;
+    if ((uniforms.flags & DISTANCE_ESTIMATION) != 0u && i < uniforms.iterations) {
+        let r = length(z);
+        de = 0.5 * r * log(r) / length(dz) / uniforms.scale;
+    }
+
+    // Smoothed iteration count, available to the colour expression whether or not smoothing is
+    // enabled. Running a couple more iterations reduces the error in the approximation.
+    var sn = n;
+    var smooth_z = final_z;
+    if (i > 0) {
+        var z = final_z;
         z = REPLACE_FRACTAL_EQN;
         z = REPLACE_FRACTAL_EQN;
+        smooth_z = z;
+
+        // The formula assumes |z| only just passed the escape threshold, so it has to use the same
+        // threshold to stay continuous between iteration counts. With thresholds close to 1, |z|
+        // can fall back below the threshold during the extra iterations, and with very large
+        // ones it can overflow, both of which give NaN or infinity without the clamping.
+        let log_bailout = max(log(uniforms.escape_threshold), 0.001);
+        let log_ratio = log(max(log(length(z)) / log_bailout, 1.0));
+        sn = clamp(sn + 2.0 - log_ratio / log(max(uniforms.power, 1.001)), n, n + 2.0);
+    }
+
+    if ((uniforms.flags & SMOOTHEN) != 0u && i > 0) {
+        z = smooth_z;
+        n = mix(n, sn, uniforms.smoothing);
+    }
+
+    // Counting down from the limit reverses the direction of gradients in the colour expression
+    if ((uniforms.flags & INVERT_ITERATIONS) != 0u) {
+        n = f32(uniforms.iterations) - n;
+        sn = f32(uniforms.iterations) - sn;
+    }
+
+    if ((uniforms.flags & GREYSCALE_ITERATIONS) != 0u) {
+        return vec4(vec3(n / f32(uniforms.iterations)), 1.0);
+    }
+
+    var colour: vec3<f32>;
+    if ((uniforms.flags & PALETTE) != 0u) {
+        colour = sample_palette(log_scale(n));
+    } else {
+        colour = REPLACE_COLOR; // gets replaced by user-defined expression
+    }
+    if ((uniforms.flags & INVERT_RGB) != 0u) {
+        colour = 1.0 - colour;
+    }
+    return vec4(colour, 1.0);
+}
+
+// Newton's method on f(z) = the user-defined equation, coloured by which root it converges to
+fn get_fragment_colour_newton(start: vec2<f32>) -> vec4<f32> {
+    var z = start;
+    let c = uniforms.initial_value;
+    // Convergence is judged relative to the size of a pixel, so that zoomed in views still resolve
+    let epsilon = max(uniforms.scale * 0.01, 1e-6);
+
+    var i: i32 = 0;
+    var converged = false;
+    for (; i < uniforms.iterations; i++) {
+        let dz = cdiv(REPLACE_FRACTAL_EQN, REPLACE_DERIVATIVE); // gets replaced by user-defined expressions
+        z -= dz;
+        if (length(dz) < epsilon) {
+            converged = true;
+            break;
+        }
+    }
+
+    if ((uniforms.flags & ITERATION_PROBE) != 0u) {
+        return encode_iterations(i);
+    }
+    if (!converged) {
+        return solid_interior(z, c, trap_distance(z), 0);
+    }
+
+    let shade = 1.0 - 0.7 * log(f32(i) + 1.0) / log(f32(uniforms.iterations) + 1.0);
+    if (uniforms.root_count == 0u) {
+        return vec4<f32>(shade, shade, shade, 1.0);
+    }
 
-        n += 2.0 - log2(log(length(z)));
+    var root = 0u;
+    var nearest = length(z - uniforms.roots[0].xy);
+    for (var r = 1u; r < uniforms.root_count; r++) {
+        let dist = length(z - uniforms.roots[r].xy);
+        if (dist < nearest) {
+            nearest = dist;
+            root = r;
+        }
+    }
+    return vec4<f32>(hsv_rgb(vec3<f32>(f32(root) / f32(uniforms.root_count), 0.8, shade)), 1.0);
+}
+
+fn sample_colour(pos: vec2<f32>) -> vec4<f32> {
+    if ((uniforms.flags & NEWTON) != 0u) {
+        return get_fragment_colour_newton(pos * uniforms.scale - uniforms.centre);
+    }
+    if ((uniforms.flags & HIGH_PRECISION) != 0u) {
+        let scale = vec2<f32>(uniforms.scale, uniforms.scale_lo);
+        let re = ds_sub(ds_mul(vec2<f32>(pos.x, 0.0), scale), vec2<f32>(uniforms.centre.x, uniforms.centre_lo.x));
+        let im = ds_sub(ds_mul(vec2<f32>(pos.y, 0.0), scale), vec2<f32>(uniforms.centre.y, uniforms.centre_lo.y));
+        return get_fragment_colour_hp(vec4<f32>(re, im));
     }
+    return get_fragment_colour(pos * uniforms.scale - uniforms.centre);
+}
 
-    return vec4(REPLACE_COLOR, 1.0); // gets replaced by user-defined expression
+fn linear_to_srgb(colour: vec3<f32>) -> vec3<f32> {
+    let low = colour * 12.92;
+    let high = 1.055 * pow(colour, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, colour <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(colour: vec3<f32>) -> vec3<f32> {
+    let low = colour / 12.92;
+    let high = pow((colour + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, colour <= vec3<f32>(0.04045));
 }
 
 @fragment
 fn fs_main(@builtin(position) in: vec4<f32>) -> @location(0) vec4<f32> {
-    return get_fragment_colour(in.xy * uniforms.scale - uniforms.centre);
-}
\ No newline at end of file
+    // Averaging encoded iteration counts would give nonsense, so the probe always takes one sample
+    var samples = max(uniforms.samples, 1u);
+    if ((uniforms.flags & ITERATION_PROBE) != 0u) {
+        samples = 1u;
+    }
+
+    var colour = vec4<f32>(0.0);
+    if ((uniforms.flags & EDGE_ANTIALIASING) != 0u && (uniforms.flags & ITERATION_PROBE) == 0u) {
+        colour = edge_antialiased_colour(in.xy);
+    } else {
+        for (var y = 0u; y < samples; y++) {
+            for (var x = 0u; x < samples; x++) {
+                // Evenly spaced subpixel offsets, centred on the pixel
+                let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / f32(samples) - 0.5;
+                colour += sample_colour(in.xy + offset);
+            }
+        }
+        colour /= f32(samples * samples);
+    }
+
+    // Colour expressions give sRGB values, which an sRGB target would encode a second time
+    if ((uniforms.flags & SRGB_TARGET) != 0u && (uniforms.flags & ITERATION_PROBE) == 0u) {
+        colour = vec4<f32>(srgb_to_linear(colour.rgb), colour.a);
+    }
+    return colour;
+}
@@ -1,8 +1,90 @@
-use crate::settings::UserSettings;
-use eframe::egui::Vec2;
+use crate::settings::{FractalMode, Framing, InvertColours, UserSettings, MAX_ROOTS};
+use bytemuck::Zeroable;
+use eframe::egui::{Pos2, Vec2};
 
-pub(crate) fn calculate_scale(size: Vec2, settings: &UserSettings) -> f32 {
-    4.0 / settings.zoom / size.min_elem()
+/// Makes the shader output the iteration count instead of a colour (see `encode_iterations`)
+pub(crate) const ITERATION_PROBE: u32 = 1 << 4;
+/// The render target is an sRGB format, so colours need converting to linear before output
+pub(crate) const SRGB_TARGET: u32 = 1 << 9;
+/// Replaces the colour expression with a greyscale map of the iteration count
+pub(crate) const GREYSCALE_ITERATIONS: u32 = 1 << 10;
+/// With `ITERATION_PROBE`, output the iterations actually run rather than treating points
+/// skipped by the interior check as reaching the limit
+pub(crate) const ITERATION_STATS: u32 = 1 << 11;
+
+pub(crate) fn calculate_scale(size: Vec2, settings: &UserSettings) -> f64 {
+    let extent = match settings.framing {
+        Framing::ShorterSide => size.min_elem(),
+        Framing::Width => size.x,
+    };
+    let scale = 4.0 / settings.zoom / extent as f64;
+    // A zero zoom or an empty view (e.g. a minimised window) would make every calculation using
+    // the scale give infinity or NaN, which then gets stuck in the centre
+    if scale.is_finite() && scale > 0.0 {
+        scale
+    } else {
+        4.0
+    }
+}
+
+/// The iteration count to render with, which is raised with the zoom depth if auto iterations
+/// are on. The user's value is used as the base, and is never reduced.
+pub(crate) fn effective_iterations(settings: &UserSettings) -> i32 {
+    if !settings.auto_iterations || settings.zoom <= 1.0 {
+        return settings.iterations;
+    }
+    let limit = settings.auto_iterations_limit.max(settings.iterations);
+    let scaled = settings.iterations as f64 + settings.auto_iterations_rate * settings.zoom.log2();
+    // Casting saturates, so this also clamps to i32::MAX
+    (scaled.min(limit as f64) as i32).max(settings.iterations)
+}
+
+/// The point in the complex plane shown at `pos` in a view of the given size. Everything is done
+/// in f64, so this stays accurate at zoom levels where high precision mode is needed.
+pub(crate) fn pointer_to_complex(pos: Pos2, size: Vec2, settings: &UserSettings) -> [f64; 2] {
+    let scale = calculate_scale(size, settings);
+    [
+        (pos.x as f64 - size.x as f64 / 2.0) * scale + settings.centre[0],
+        (pos.y as f64 - size.y as f64 / 2.0) * scale + settings.centre[1],
+    ]
+}
+
+/// Where the point `z` in the complex plane is shown in a view of the given size, the inverse of
+/// `pointer_to_complex`
+pub(crate) fn complex_to_pointer(z: [f64; 2], size: Vec2, settings: &UserSettings) -> Pos2 {
+    let scale = calculate_scale(size, settings);
+    Pos2::new(
+        ((z[0] - settings.centre[0]) / scale + size.x as f64 / 2.0) as f32,
+        ((z[1] - settings.centre[1]) / scale + size.y as f64 / 2.0) as f32,
+    )
+}
+
+fn uses_high_precision(settings: &UserSettings) -> bool {
+    settings.high_precision
+        && settings.shader_data.is_standard_equation()
+        && settings.shader_data.has_default_escape_condition()
+}
+
+/// Whether the view is zoomed in so far that neighbouring pixels can't be told apart at the
+/// precision the shader works in
+pub(crate) fn precision_exhausted(size: Vec2, settings: &UserSettings) -> bool {
+    let scale = calculate_scale(size, settings);
+    let epsilon = if uses_high_precision(settings) {
+        f32::EPSILON as f64 * f32::EPSILON as f64
+    } else {
+        f32::EPSILON as f64
+    };
+    let magnitude = settings.centre[0]
+        .abs()
+        .max(settings.centre[1].abs())
+        .max(size.max_elem() as f64 * scale);
+    (scale as f32) < f32::MIN_POSITIVE || scale < magnitude * epsilon
+}
+
+/// Split an f64 into a high and low f32 part, for double-single arithmetic in the shader
+fn split_f64(value: f64) -> (f32, f32) {
+    let hi = value as f32;
+    (hi, (value - hi as f64) as f32)
 }
 
 #[repr(C)]
@@ -14,24 +96,172 @@ pub(crate) struct Uniforms {
     iterations: i32,
     flags: u32,
     initial_value: [f32; 2],
+    centre_lo: [f32; 2],
+    initial_value_lo: [f32; 2],
+    scale_lo: f32,
+    samples: u32,
+    root_count: u32,
+    _padding: u32,
+    /// Only the first two components of each root are used, for WGSL array alignment
+    roots: [[f32; 4]; MAX_ROOTS],
+    trap_centre: [f32; 2],
+    trap_shape: u32,
+    trap_angle: f32,
+    power: f32,
+    time: f32,
+    _padding_2: [u32; 2],
+    /// Only the first three components are used, for WGSL vec3 alignment
+    interior_colour: [f32; 4],
+    morph_c: [f32; 2],
+    morph: f32,
+    smoothing: f32,
+}
+
+/// How far the view is morphed towards a Julia set, or 0 if it can't be
+pub(crate) fn julia_morph(settings: &UserSettings) -> f32 {
+    if settings.julia_set
+        || settings.mode != FractalMode::EscapeTime
+        || !settings.shader_data.supports_julia_morph()
+    {
+        return 0.0;
+    }
+    settings.julia_morph.clamp(0.0, 1.0)
 }
 
 impl Uniforms {
     pub(crate) fn new(size: Vec2, settings: &UserSettings) -> Self {
         let scale = calculate_scale(size, settings);
-        Uniforms {
+        let morph = julia_morph(settings);
+        // Neither high precision mode nor the interior check know about morphing
+        let high_precision = uses_high_precision(settings) && morph == 0.0;
+        // Period colouring is only done for the standard Mandelbrot set, in place of the solid fill
+        // of the inside of the set, and high precision mode doesn't support it
+        let period_colouring = settings.period_colouring
+            && settings.internal_black
+            && !settings.julia_set
+            && settings.mode == FractalMode::EscapeTime
+            && settings.shader_data.is_standard_equation()
+            && !high_precision;
+        // The interior check only recognises the standard Mandelbrot set starting from z = 0, and
+        // can only skip iterating when the result will be black anyway. High precision mode
+        // doesn't use it, as the single precision check isn't reliable there. Skipping points
+        // also skips finding their period and final value of z, which the interior colour
+        // expression might use.
+        let interior_check = settings.interior_check
+            && settings.internal_black
+            && !settings.julia_set
+            && settings.initial_value == [0.0, 0.0]
+            && settings.shader_data.is_standard_equation()
+            && settings.shader_data.has_default_escape_condition()
+            && morph == 0.0
+            && !period_colouring
+            && settings.shader_data.has_solid_interior();
+        let distance_estimation =
+            settings.distance_estimation && settings.shader_data.supports_distance_estimation();
+        // Edge antialiasing blends the filled inside of the set with the outside, so there is
+        // nothing to do without a fill. Supersampling already smooths the edge, and the orbits it
+        // reuses have no high precision version.
+        let edge_antialiasing = settings.edge_antialiasing
+            && settings.internal_black
+            && settings.samples <= 1
+            && settings.mode == FractalMode::EscapeTime
+            && !high_precision;
+
+        let (scale, scale_lo) = split_f64(scale);
+        let mut uniforms = Uniforms {
             scale,
-            centre: [
-                size.x / 2.0 * scale - settings.centre[0],
-                size.y / 2.0 * scale - settings.centre[1],
-            ],
-            iterations: settings.iterations,
-            flags: (settings.initial_c as u32) << 3
+            scale_lo,
+            iterations: effective_iterations(settings),
+            flags: ((distance_estimation && settings.shader_data.is_holomorphic_power()) as u32)
+                << 17
+                | (edge_antialiasing as u32) << 16
+                | ((settings.use_palette && !settings.palette.is_empty()) as u32) << 15
+                | (period_colouring as u32) << 14
+                | ((settings.invert_colours == InvertColours::Rgb) as u32) << 13
+                | ((settings.invert_colours == InvertColours::Iterations) as u32) << 12
+                | (interior_check as u32) << 8
+                | ((settings.mode == FractalMode::Newton) as u32) << 7
+                | (distance_estimation as u32) << 6
+                | (high_precision as u32) << 5
+                | (settings.initial_c as u32) << 3
                 | (settings.internal_black as u32) << 2
                 | (settings.smoothen as u32) << 1
                 | (settings.julia_set as u32),
-            initial_value: settings.initial_value,
             escape_threshold: settings.escape_threshold,
+            samples: settings.samples.clamp(1, 4),
+            root_count: settings.roots.len().min(MAX_ROOTS) as u32,
+            trap_centre: settings.orbit_trap.centre.map(|x| x as f32),
+            trap_shape: settings.orbit_trap.shape as u32,
+            trap_angle: settings.orbit_trap.angle,
+            interior_colour: {
+                let [r, g, b] = settings.interior_colour.map(|x| x as f32 / 255.0);
+                [r, g, b, 1.0]
+            },
+            power: settings
+                .shader_data
+                .equation_power()
+                .unwrap_or(settings.smoothing_power),
+            morph_c: settings.julia_c.map(|x| x as f32),
+            morph,
+            smoothing: settings.smoothing_strength.clamp(0.0, 1.0),
+            ..Self::zeroed()
+        };
+        for (uniform, root) in uniforms.roots.iter_mut().zip(&settings.roots) {
+            *uniform = [root[0] as f32, root[1] as f32, 0.0, 0.0];
         }
+        uniforms.set_centre([
+            size.x as f64 / 2.0 * uniforms.scale() - settings.centre[0],
+            size.y as f64 / 2.0 * uniforms.scale() - settings.centre[1],
+        ]);
+        // The shader uses the same value as the initial value of z, or as c for Julia sets
+        let initial_value = if settings.julia_set && settings.mode == FractalMode::EscapeTime {
+            settings.julia_c
+        } else {
+            settings.initial_value
+        };
+        (uniforms.initial_value[0], uniforms.initial_value_lo[0]) = split_f64(initial_value[0]);
+        (uniforms.initial_value[1], uniforms.initial_value_lo[1]) = split_f64(initial_value[1]);
+        uniforms
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale as f64 + self.scale_lo as f64
+    }
+
+    fn centre(&self) -> [f64; 2] {
+        [
+            self.centre[0] as f64 + self.centre_lo[0] as f64,
+            self.centre[1] as f64 + self.centre_lo[1] as f64,
+        ]
+    }
+
+    fn set_centre(&mut self, centre: [f64; 2]) {
+        (self.centre[0], self.centre_lo[0]) = split_f64(centre[0]);
+        (self.centre[1], self.centre_lo[1]) = split_f64(centre[1]);
+    }
+
+    /// Shift the rendered region so that the top-left of the render target shows the point that
+    /// would be at `origin` in a target of the size these uniforms were created for.
+    pub(crate) fn offset(mut self, origin: Vec2) -> Self {
+        let scale = self.scale();
+        let [x, y] = self.centre();
+        self.set_centre([x - origin.x as f64 * scale, y - origin.y as f64 * scale]);
+        self
+    }
+
+    /// Set the time in seconds that colour expressions can use to animate the palette
+    pub(crate) fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub(crate) fn with_iterations(mut self, iterations: i32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub(crate) fn with_flags(mut self, flags: u32) -> Self {
+        self.flags |= flags;
+        self
     }
 }
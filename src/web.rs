@@ -1,6 +1,94 @@
-use crate::FractalViewerApp;
+use crate::fallback::{show_web_error, RendererUnavailableApp};
+use crate::{FractalViewerApp, UserSettings};
+use eframe::egui;
+use std::cell::RefCell;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlCanvasElement;
+use web_sys::{AddEventListenerOptions, ClipboardEvent, HtmlCanvasElement};
+
+const SETTINGS_STORAGE_KEY: &str = "fractal_viewer_settings";
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// The settings string saved by the last session, if there is one
+pub(crate) fn load_settings() -> Option<String> {
+    local_storage()?.get_item(SETTINGS_STORAGE_KEY).ok()?
+}
+
+pub(crate) fn save_settings(settings: &UserSettings) {
+    let saved = local_storage()
+        .map(|storage| storage.set_item(SETTINGS_STORAGE_KEY, &settings.export_string()));
+    if !matches!(saved, Some(Ok(()))) {
+        log::warn!("Failed to save settings to local storage");
+    }
+}
+
+/// Copy, cut and paste events from the page, waiting to be given to egui. They are caught on the
+/// window before anything else on the page sees them, while an egui text field such as the
+/// equation editor has keyboard focus, so they reach it wherever the browser sends them.
+#[derive(Clone, Default)]
+pub(crate) struct ClipboardEvents(Rc<RefCell<Vec<egui::Event>>>);
+
+impl ClipboardEvents {
+    pub(crate) fn install(ctx: &egui::Context) -> Self {
+        let events = Self::default();
+        let Some(window) = web_sys::window() else {
+            return events;
+        };
+
+        let options = AddEventListenerOptions::new();
+        options.set_capture(true);
+        for name in ["copy", "cut", "paste"] {
+            let queue = events.clone();
+            let ctx = ctx.clone();
+            let listener =
+                Closure::<dyn FnMut(ClipboardEvent)>::new(move |event: ClipboardEvent| {
+                    // Anything else on the page keeps the browser's own handling
+                    if !ctx.wants_keyboard_input() {
+                        return;
+                    }
+                    let forwarded = match event.type_().as_str() {
+                        "copy" => egui::Event::Copy,
+                        "cut" => egui::Event::Cut,
+                        _ => match event
+                            .clipboard_data()
+                            .and_then(|data| data.get_data("text/plain").ok())
+                        {
+                            Some(text) if !text.is_empty() => {
+                                egui::Event::Paste(text.replace("\r\n", "\n"))
+                            }
+                            _ => return,
+                        },
+                    };
+                    // Otherwise eframe's own handlers would give egui the event a second time
+                    event.prevent_default();
+                    event.stop_immediate_propagation();
+                    queue.0.borrow_mut().push(forwarded);
+                    ctx.request_repaint();
+                });
+            if window
+                .add_event_listener_with_callback_and_add_event_listener_options(
+                    name,
+                    listener.as_ref().unchecked_ref(),
+                    &options,
+                )
+                .is_err()
+            {
+                log::warn!("Failed to listen for {name} events");
+            }
+            // The listeners stay for as long as the page is open
+            listener.forget();
+        }
+        events
+    }
+
+    /// Move the events caught since the last frame into egui's input for this one
+    pub(crate) fn drain_into(&self, events: &mut Vec<egui::Event>) {
+        events.append(&mut self.0.borrow_mut());
+    }
+}
 
 #[wasm_bindgen(start)]
 async fn wasm_main() -> Result<(), JsValue> {
@@ -14,11 +102,18 @@ async fn wasm_main() -> Result<(), JsValue> {
         .expect("fv_canvas was not an HtmlCanvasElement!");
 
     let runner = eframe::WebRunner::new();
-    runner
+    let result = runner
         .start(
-            canvas,
+            canvas.clone(),
             eframe::WebOptions::default(),
-            Box::new(|cc| Ok(Box::new(FractalViewerApp::new(cc).unwrap()))),
+            Box::new(|cc| match FractalViewerApp::new(cc) {
+                Some(app) => Ok(Box::new(app)),
+                None => Ok(Box::new(RendererUnavailableApp)),
+            }),
         )
-        .await
+        .await;
+    if let Err(e) = &result {
+        show_web_error(&canvas, &format!("{e:?}"));
+    }
+    result
 }